@@ -0,0 +1,68 @@
+use std::time::Duration;
+
+use rusb::Context;
+use rust_dualsense::{Bus, DualSense, SimulatedDualSense, Transport, UsbTransport};
+
+fn convert_argument(input: &str) -> u16 {
+    if input.starts_with("0x") {
+        return u16::from_str_radix(input.trim_start_matches("0x"), 16).unwrap();
+    }
+    input
+        .parse()
+        .expect("Invalid input, be sure to add `0x` for hexadecimal values.")
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+
+    if args.get(1).map(String::as_str) == Some("--simulated") {
+        read_loop(DualSense::new(SimulatedDualSense::new(Bus::Usb)));
+        return;
+    }
+
+    if args.len() < 3 {
+        println!("usage: read_device <base-10/0xbase-16> <base-10/0xbase-16>");
+        println!("       read_device --simulated");
+        return;
+    }
+
+    let vid = convert_argument(args[1].as_ref());
+    let pid = convert_argument(args[2].as_ref());
+
+    let mut context = match Context::new() {
+        Ok(context) => context,
+        Err(e) => panic!("could not initialize libusb: {}", e),
+    };
+
+    match UsbTransport::open_with(&mut context, vid, pid) {
+        Ok(transport) => read_device(DualSense::new(transport)),
+        Err(e) => println!("{}", e),
+    }
+}
+
+fn read_device(dualsense: DualSense) {
+    let transport = dualsense.transport();
+
+    println!("Manufacturer: {:?}", transport.manufacturer().ok());
+    println!("Product: {:?}", transport.product().ok());
+    println!("Serial Number: {:?}", transport.serial_number().ok());
+
+    println!("Reading from endpoint: {:?}", transport.input_endpoint());
+    println!(" - kernel driver? {}", transport.had_kernel_driver());
+
+    read_loop(dualsense);
+}
+
+fn read_loop<T: Transport>(mut dualsense: DualSense<T>) {
+    let timeout = Duration::from_secs(1);
+    loop {
+        match dualsense.read_state(timeout) {
+            Ok(state) => println!("{:?}", state),
+            Err(err) => println!(
+                "could not read report: {} ({} dropped)",
+                err,
+                dualsense.dropped_reports()
+            ),
+        }
+    }
+}
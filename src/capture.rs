@@ -0,0 +1,379 @@
+//! Recording raw reports to a file and playing them back.
+//!
+//! A capture starts with a header describing the controller, followed by one
+//! record per report. Everything is little-endian:
+//!
+//! ```text
+//! header:  b"DSCAP" version:u8 vid:u16 pid:u16 bus:u8 firmware:u32
+//!          serial_len:u16 serial:[u8]
+//! record:  direction:u8 timestamp_us:u64 len:u16 data:[u8]
+//! ```
+
+use std::io::{self, Read, Write};
+use std::time::{Duration, Instant};
+
+use crate::clock::{Clock, SystemClock};
+use crate::device::Bus;
+use crate::input::{InputParser, InputState};
+use crate::transport::Transport;
+use crate::{Error, Result};
+
+const MAGIC: &[u8; 5] = b"DSCAP";
+const VERSION: u8 = 1;
+
+/// Description of the captured controller.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CaptureHeader {
+    pub vendor_id: u16,
+    pub product_id: u16,
+    pub bus: Bus,
+    pub serial: String,
+    /// Firmware version from feature report 0x20, 0 when unknown.
+    pub firmware_version: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Input,
+    Output,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CaptureRecord {
+    /// Time since the capture started.
+    pub timestamp: Duration,
+    pub direction: Direction,
+    /// The raw report, report id included.
+    pub data: Vec<u8>,
+}
+
+pub struct CaptureWriter<W: Write> {
+    writer: W,
+    start: Instant,
+}
+
+impl<W: Write> CaptureWriter<W> {
+    /// Writes the header. Record timestamps count from this call.
+    ///
+    /// Fails with [`Error::InvalidCapture`] if the serial is longer than
+    /// the format allows.
+    pub fn new(mut writer: W, header: &CaptureHeader) -> Result<Self> {
+        let serial = header.serial.as_bytes();
+        let serial_len = u16::try_from(serial.len())
+            .map_err(|_| Error::InvalidCapture("serial is longer than 65535 bytes"))?;
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[VERSION])?;
+        writer.write_all(&header.vendor_id.to_le_bytes())?;
+        writer.write_all(&header.product_id.to_le_bytes())?;
+        writer.write_all(&[bus_to_u8(header.bus)])?;
+        writer.write_all(&header.firmware_version.to_le_bytes())?;
+        writer.write_all(&serial_len.to_le_bytes())?;
+        writer.write_all(serial)?;
+        Ok(CaptureWriter {
+            writer,
+            start: Instant::now(),
+        })
+    }
+
+    /// Appends a record. Fails with [`Error::InvalidCapture`], writing
+    /// nothing, if the report is longer than the format allows.
+    pub fn write_record(&mut self, record: &CaptureRecord) -> Result<()> {
+        let len = u16::try_from(record.data.len())
+            .map_err(|_| Error::InvalidCapture("report is longer than 65535 bytes"))?;
+        let direction = match record.direction {
+            Direction::Input => 0u8,
+            Direction::Output => 1u8,
+        };
+        self.writer.write_all(&[direction])?;
+        self.writer
+            .write_all(&(record.timestamp.as_micros() as u64).to_le_bytes())?;
+        self.writer.write_all(&len.to_le_bytes())?;
+        self.writer.write_all(&record.data)?;
+        Ok(())
+    }
+
+    /// Records `data` as seen now.
+    pub fn record(&mut self, direction: Direction, data: &[u8]) -> Result<()> {
+        self.write_record(&CaptureRecord {
+            timestamp: self.start.elapsed(),
+            direction,
+            data: data.to_vec(),
+        })
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        Ok(self.writer.flush()?)
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+pub struct CaptureReader<R: Read> {
+    reader: R,
+    header: CaptureHeader,
+}
+
+impl<R: Read> CaptureReader<R> {
+    /// Reads and checks the header.
+    pub fn new(mut reader: R) -> Result<Self> {
+        let mut magic = [0; 5];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(Error::InvalidCapture("not a capture file"));
+        }
+        if read_u8(&mut reader)? != VERSION {
+            return Err(Error::InvalidCapture("unsupported capture version"));
+        }
+        let vendor_id = read_u16(&mut reader)?;
+        let product_id = read_u16(&mut reader)?;
+        let bus = match read_u8(&mut reader)? {
+            0 => Bus::Usb,
+            1 => Bus::Bluetooth,
+            _ => return Err(Error::InvalidCapture("unknown bus")),
+        };
+        let firmware_version = read_u32(&mut reader)?;
+        let mut serial = vec![0; read_u16(&mut reader)? as usize];
+        reader.read_exact(&mut serial)?;
+        let serial =
+            String::from_utf8(serial).map_err(|_| Error::InvalidCapture("serial is not utf-8"))?;
+
+        Ok(CaptureReader {
+            reader,
+            header: CaptureHeader {
+                vendor_id,
+                product_id,
+                bus,
+                serial,
+                firmware_version,
+            },
+        })
+    }
+
+    pub fn header(&self) -> &CaptureHeader {
+        &self.header
+    }
+
+    /// Reads the next record, `None` at the end of the capture.
+    pub fn next_record(&mut self) -> Result<Option<CaptureRecord>> {
+        let mut direction = [0];
+        match self.reader.read_exact(&mut direction) {
+            Ok(()) => (),
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e.into()),
+        }
+        let direction = match direction[0] {
+            0 => Direction::Input,
+            1 => Direction::Output,
+            _ => return Err(Error::InvalidCapture("unknown record direction")),
+        };
+        let timestamp = Duration::from_micros(read_u64(&mut self.reader)?);
+        let mut data = vec![0; read_u16(&mut self.reader)? as usize];
+        self.reader.read_exact(&mut data)?;
+        Ok(Some(CaptureRecord {
+            timestamp,
+            direction,
+            data,
+        }))
+    }
+}
+
+impl<R: Read> Iterator for CaptureReader<R> {
+    type Item = Result<CaptureRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_record().transpose()
+    }
+}
+
+/// Transport wrapper that records every input and output report passing
+/// through it.
+pub struct Recorder<T: Transport, W: Write> {
+    transport: T,
+    writer: CaptureWriter<W>,
+}
+
+impl<T: Transport, W: Write> Recorder<T, W> {
+    pub fn new(transport: T, writer: CaptureWriter<W>) -> Self {
+        Recorder { transport, writer }
+    }
+
+    pub fn into_parts(self) -> (T, CaptureWriter<W>) {
+        (self.transport, self.writer)
+    }
+}
+
+impl<T: Transport, W: Write> Transport for Recorder<T, W> {
+    fn bus(&self) -> Bus {
+        self.transport.bus()
+    }
+
+    fn read_input(&mut self, buf: &mut [u8], timeout: Duration) -> Result<usize> {
+        let len = self.transport.read_input(buf, timeout)?;
+        self.writer.record(Direction::Input, &buf[..len])?;
+        Ok(len)
+    }
+
+    fn write_output(&mut self, report: &[u8]) -> Result<usize> {
+        let len = self.transport.write_output(report)?;
+        self.writer.record(Direction::Output, report)?;
+        Ok(len)
+    }
+
+    fn get_feature(&mut self, report_id: u8, buf: &mut [u8]) -> Result<usize> {
+        self.transport.get_feature(report_id, buf)
+    }
+
+    fn set_feature(&mut self, report: &[u8]) -> Result<()> {
+        self.transport.set_feature(report)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReplaySpeed {
+    /// Keep the gaps between reports as recorded.
+    Original,
+    /// Multiply the playback rate, 2.0 plays twice as fast. A rate that is
+    /// not a positive number, such as 0 or NaN, plays like [`ReplaySpeed::Max`].
+    Scaled(f64),
+    /// Hand out reports as fast as they are asked for.
+    Max,
+}
+
+/// Plays the input reports of a capture back, as a [`Transport`] or as
+/// parsed states. Output reports in the capture are skipped.
+///
+/// Reports are timed on a [`Clock`], counting from the first one handed out.
+pub struct Replay<R: Read, C: Clock = SystemClock> {
+    reader: CaptureReader<R>,
+    speed: ReplaySpeed,
+    parser: InputParser,
+    clock: C,
+    start: Option<(Duration, Duration)>,
+}
+
+impl<R: Read> Replay<R, SystemClock> {
+    pub fn new(reader: CaptureReader<R>, speed: ReplaySpeed) -> Self {
+        Self::with_clock(reader, speed, SystemClock::new())
+    }
+}
+
+impl<R: Read, C: Clock> Replay<R, C> {
+    pub fn with_clock(reader: CaptureReader<R>, speed: ReplaySpeed, clock: C) -> Self {
+        Replay {
+            reader,
+            speed,
+            parser: InputParser::new(),
+            clock,
+            start: None,
+        }
+    }
+
+    pub fn header(&self) -> &CaptureHeader {
+        self.reader.header()
+    }
+
+    /// Waits until the next input report is due and returns it, `None` at the
+    /// end of the capture.
+    pub fn next_input(&mut self) -> Result<Option<CaptureRecord>> {
+        loop {
+            let record = match self.reader.next_record()? {
+                Some(record) => record,
+                None => return Ok(None),
+            };
+            if record.direction != Direction::Input {
+                continue;
+            }
+            self.wait_for(record.timestamp);
+            return Ok(Some(record));
+        }
+    }
+
+    /// Parses the next input report, `None` at the end of the capture.
+    pub fn next_state(&mut self) -> Result<Option<InputState>> {
+        match self.next_input()? {
+            Some(record) => self.parser.parse(&record.data).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Reports dropped by [`Replay::next_state`] because of a bad checksum.
+    pub fn dropped(&self) -> u64 {
+        self.parser.dropped()
+    }
+
+    fn wait_for(&mut self, timestamp: Duration) {
+        let rate = match self.speed {
+            ReplaySpeed::Original => 1.0,
+            ReplaySpeed::Scaled(rate) if rate > 0.0 => rate,
+            _ => return,
+        };
+        let (started, first) = *self.start.get_or_insert((self.clock.now(), timestamp));
+        // A tiny rate can stretch the gap beyond what a Duration holds.
+        let offset = timestamp.saturating_sub(first).as_secs_f64() / rate;
+        let offset = Duration::try_from_secs_f64(offset).unwrap_or(Duration::MAX);
+        let due = started.saturating_add(offset);
+        let now = self.clock.now();
+        if due > now {
+            self.clock.sleep(due - now);
+        }
+    }
+}
+
+impl<R: Read, C: Clock> Transport for Replay<R, C> {
+    fn bus(&self) -> Bus {
+        self.reader.header().bus
+    }
+
+    /// Returns [`Error::Timeout`] once the capture is exhausted.
+    fn read_input(&mut self, buf: &mut [u8], _timeout: Duration) -> Result<usize> {
+        let record = self.next_input()?.ok_or(Error::Timeout)?;
+        let len = record.data.len().min(buf.len());
+        buf[..len].copy_from_slice(&record.data[..len]);
+        Ok(len)
+    }
+
+    fn write_output(&mut self, report: &[u8]) -> Result<usize> {
+        Ok(report.len())
+    }
+
+    fn get_feature(&mut self, report_id: u8, _buf: &mut [u8]) -> Result<usize> {
+        Err(Error::UnsupportedReport(report_id))
+    }
+
+    fn set_feature(&mut self, _report: &[u8]) -> Result<()> {
+        Ok(())
+    }
+}
+
+fn bus_to_u8(bus: Bus) -> u8 {
+    match bus {
+        Bus::Usb => 0,
+        Bus::Bluetooth => 1,
+    }
+}
+
+fn read_u8<R: Read>(reader: &mut R) -> io::Result<u8> {
+    let mut buf = [0; 1];
+    reader.read_exact(&mut buf)?;
+    Ok(buf[0])
+}
+
+fn read_u16<R: Read>(reader: &mut R) -> io::Result<u16> {
+    let mut buf = [0; 2];
+    reader.read_exact(&mut buf)?;
+    Ok(u16::from_le_bytes(buf))
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> io::Result<u32> {
+    let mut buf = [0; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> io::Result<u64> {
+    let mut buf = [0; 8];
+    reader.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
@@ -0,0 +1,80 @@
+//! Time sources for the effects that run on a schedule.
+//!
+//! Animations and schedulers read the time through [`Clock`] so they can be
+//! driven by [`FakeClock`] in tests and get identical results on every run.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub trait Clock {
+    /// Time elapsed since some fixed point of the clock.
+    fn now(&self) -> Duration;
+
+    /// Blocks until `duration` has passed on this clock.
+    fn sleep(&self, duration: Duration);
+}
+
+/// Wall clock time, counted from the clock's creation.
+#[derive(Debug, Clone, Copy)]
+pub struct SystemClock {
+    start: Instant,
+}
+
+impl SystemClock {
+    pub fn new() -> Self {
+        SystemClock {
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        thread::sleep(duration);
+    }
+}
+
+/// Clock that only moves when told to. Clones share the same time, so one
+/// copy can be handed to an animator while the test keeps the other.
+#[derive(Debug, Clone, Default)]
+pub struct FakeClock {
+    nanos: Arc<AtomicU64>,
+}
+
+impl FakeClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        self.nanos
+            .fetch_add(duration.as_nanos() as u64, Ordering::SeqCst);
+    }
+
+    pub fn set(&self, now: Duration) {
+        self.nanos.store(now.as_nanos() as u64, Ordering::SeqCst);
+    }
+}
+
+impl Clock for FakeClock {
+    fn now(&self) -> Duration {
+        Duration::from_nanos(self.nanos.load(Ordering::SeqCst))
+    }
+
+    /// Advances the clock instead of blocking.
+    fn sleep(&self, duration: Duration) {
+        self.advance(duration);
+    }
+}
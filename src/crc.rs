@@ -0,0 +1,54 @@
+//! CRC-32 as used by the Bluetooth reports.
+//!
+//! The checksum covers a one byte seed followed by the report. The seed is
+//! 0xA1 for input reports, 0xA2 for output reports and 0xA3 for feature
+//! reports.
+
+pub(crate) const SEED_INPUT: u8 = 0xa1;
+pub(crate) const SEED_OUTPUT: u8 = 0xa2;
+pub(crate) const SEED_FEATURE: u8 = 0xa3;
+
+const TABLE: [u32; 256] = make_table();
+
+const fn make_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+fn update(crc: u32, data: &[u8]) -> u32 {
+    data.iter().fold(crc, |crc, &byte| {
+        TABLE[((crc ^ byte as u32) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
+
+/// Computes the checksum of `seed` followed by `data`.
+pub(crate) fn crc32(seed: u8, data: &[u8]) -> u32 {
+    !update(update(0xffff_ffff, &[seed]), data)
+}
+
+/// Checks the little-endian checksum stored in the last four bytes of `report`.
+pub(crate) fn verify(seed: u8, report: &[u8]) -> Result<(), (u32, u32)> {
+    let (data, tail) = report.split_at(report.len() - 4);
+    let expected = u32::from_le_bytes([tail[0], tail[1], tail[2], tail[3]]);
+    let actual = crc32(seed, data);
+    if expected == actual {
+        Ok(())
+    } else {
+        Err((expected, actual))
+    }
+}
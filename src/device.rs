@@ -0,0 +1,157 @@
+use std::time::Duration;
+
+use crate::events::{EventGenerator, Events};
+use crate::imu::{ImuCalibration, MotionSample, CALIBRATION_REPORT_SIZE};
+use crate::info::{DeviceInfo, FirmwareInfo, PairingInfo, FIRMWARE_INFO_SIZE, PAIRING_INFO_SIZE};
+use crate::input::{InputParser, InputState, BT_INPUT_REPORT_SIZE};
+use crate::output::OutputReport;
+use crate::transport::usb::UsbTransport;
+use crate::transport::Transport;
+use crate::Result;
+
+pub const SONY_VENDOR_ID: u16 = 0x054c;
+pub const DUALSENSE_PRODUCT_ID: u16 = 0x0ce6;
+pub const DUALSENSE_EDGE_PRODUCT_ID: u16 = 0x0df2;
+pub const DUALSHOCK4_PRODUCT_ID: u16 = 0x05c4;
+pub const DUALSHOCK4_V2_PRODUCT_ID: u16 = 0x09cc;
+
+pub const FEATURE_REPORT_CALIBRATION: u8 = 0x05;
+pub const FEATURE_REPORT_PAIRING_INFO: u8 = 0x09;
+pub const FEATURE_REPORT_FIRMWARE_INFO: u8 = 0x20;
+
+/// How the controller is connected, which decides the report layouts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Bus {
+    Usb,
+    Bluetooth,
+}
+
+/// A DualSense controller reached through some [`Transport`].
+///
+/// Takes care of the report layout of the transport's bus, the Bluetooth
+/// output sequence number and counting dropped input reports.
+pub struct DualSense<T: Transport = UsbTransport> {
+    transport: T,
+    parser: InputParser,
+    output_seq: u8,
+    calibration: Option<ImuCalibration>,
+}
+
+impl DualSense<UsbTransport> {
+    /// Opens the first DualSense found over libusb.
+    pub fn open() -> Result<Self> {
+        Ok(Self::new(UsbTransport::open()?))
+    }
+}
+
+impl<T: Transport> DualSense<T> {
+    pub fn new(transport: T) -> Self {
+        DualSense {
+            transport,
+            parser: InputParser::new(),
+            output_seq: 0,
+            calibration: None,
+        }
+    }
+
+    pub fn bus(&self) -> Bus {
+        self.transport.bus()
+    }
+
+    pub fn transport(&self) -> &T {
+        &self.transport
+    }
+
+    pub fn transport_mut(&mut self) -> &mut T {
+        &mut self.transport
+    }
+
+    pub fn into_transport(self) -> T {
+        self.transport
+    }
+
+    /// Reads one raw input report into `buf` and returns its length.
+    pub fn read_input(&mut self, buf: &mut [u8], timeout: Duration) -> Result<usize> {
+        self.transport.read_input(buf, timeout)
+    }
+
+    /// Reads and parses the next input report.
+    pub fn read_state(&mut self, timeout: Duration) -> Result<InputState> {
+        let mut buf = [0; BT_INPUT_REPORT_SIZE];
+        let len = self.transport.read_input(&mut buf, timeout)?;
+        self.parser.parse(&buf[..len])
+    }
+
+    /// Motion sensor calibration, read from feature report 0x05 the first
+    /// time it is needed.
+    pub fn imu_calibration(&mut self) -> Result<ImuCalibration> {
+        if let Some(calibration) = self.calibration {
+            return Ok(calibration);
+        }
+        let mut buf = [0; CALIBRATION_REPORT_SIZE];
+        let len = self
+            .transport
+            .get_feature(FEATURE_REPORT_CALIBRATION, &mut buf)?;
+        let calibration = ImuCalibration::parse(&buf[..len], self.bus())?;
+        self.calibration = Some(calibration);
+        Ok(calibration)
+    }
+
+    /// Reads firmware and pairing information from feature reports 0x20 and
+    /// 0x09.
+    pub fn info(&mut self) -> Result<DeviceInfo> {
+        let mut buf = [0; FIRMWARE_INFO_SIZE];
+        let len = self
+            .transport
+            .get_feature(FEATURE_REPORT_FIRMWARE_INFO, &mut buf)?;
+        let firmware = FirmwareInfo::parse(&buf[..len], self.bus())?;
+
+        let mut buf = [0; PAIRING_INFO_SIZE];
+        let len = self
+            .transport
+            .get_feature(FEATURE_REPORT_PAIRING_INFO, &mut buf)?;
+        let pairing = PairingInfo::parse(&buf[..len], self.bus())?;
+
+        Ok(DeviceInfo { firmware, pairing })
+    }
+
+    /// Reads the next input report along with its calibrated motion sensor
+    /// values.
+    pub fn read_motion(&mut self, timeout: Duration) -> Result<(InputState, MotionSample)> {
+        let calibration = self.imu_calibration()?;
+        let state = self.read_state(timeout)?;
+        Ok((state, calibration.apply(&state)))
+    }
+
+    /// Iterator over button and axis events, see [`EventGenerator`].
+    pub fn events(&mut self, generator: EventGenerator) -> Events<'_, T> {
+        Events::new(self, generator)
+    }
+
+    /// Number of input reports rejected because of a bad checksum.
+    pub fn dropped_reports(&self) -> u64 {
+        self.parser.dropped()
+    }
+
+    /// Serializes `report` for the transport's bus and sends it.
+    pub fn send(&mut self, report: &OutputReport) -> Result<()> {
+        let bytes = report.to_bytes(self.bus(), self.output_seq);
+        self.output_seq = (self.output_seq + 1) & 0x0f;
+        self.transport.write_output(&bytes)?;
+        Ok(())
+    }
+
+    /// Sends a report that only ends the firmware's connection animation,
+    /// see [`OutputReport::release_leds`].
+    pub fn release_leds(&mut self) -> Result<()> {
+        self.send(OutputReport::new().release_leds())
+    }
+
+    pub fn get_feature(&mut self, report_id: u8, buf: &mut [u8]) -> Result<usize> {
+        self.transport.get_feature(report_id, buf)
+    }
+
+    pub fn set_feature(&mut self, report: &[u8]) -> Result<()> {
+        self.transport.set_feature(report)
+    }
+}
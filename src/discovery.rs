@@ -0,0 +1,390 @@
+//! Listing every connected controller.
+//!
+//! [`discover`] looks for DualSense, DualSense Edge and DualShock 4
+//! controllers through libusb and, on Linux, through hidraw. A controller
+//! plugged in over USB shows up once for each, since both can reach it; one
+//! connected over Bluetooth only shows up through hidraw.
+//!
+//! The DualSense has no USB serial number string. A libusb entry takes its
+//! serial from the pairing feature report when no kernel driver holds the
+//! controller, and otherwise from the hidraw node on the same USB port.
+//!
+//! The report layouts in this crate are the DualSense's. DualShock 4
+//! controllers are listed so a rig with mixed controllers can tell them
+//! apart.
+
+#[cfg(target_os = "linux")]
+use std::fs;
+#[cfg(target_os = "linux")]
+use std::os::unix::fs::MetadataExt;
+use std::time::Duration;
+
+use rusb::{Context, Device, DeviceDescriptor, DeviceHandle, Direction, TransferType, UsbContext};
+
+use crate::device::{
+    Bus, DualSense, DUALSENSE_EDGE_PRODUCT_ID, DUALSENSE_PRODUCT_ID, DUALSHOCK4_PRODUCT_ID,
+    DUALSHOCK4_V2_PRODUCT_ID, FEATURE_REPORT_FIRMWARE_INFO, FEATURE_REPORT_PAIRING_INFO,
+    SONY_VENDOR_ID,
+};
+use crate::info::{DeviceInfo, FirmwareInfo, PairingInfo, FIRMWARE_INFO_SIZE, PAIRING_INFO_SIZE};
+#[cfg(target_os = "linux")]
+use crate::transport::hidraw::{self, HidrawDevice, HidrawTransport};
+use crate::transport::usb::{self, UsbTransport};
+use crate::transport::Transport;
+use crate::{Error, Result};
+
+const TRANSFER_TIMEOUT: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ControllerModel {
+    DualSense,
+    DualSenseEdge,
+    DualShock4,
+}
+
+impl ControllerModel {
+    pub const ALL: [ControllerModel; 3] = [
+        ControllerModel::DualSense,
+        ControllerModel::DualSenseEdge,
+        ControllerModel::DualShock4,
+    ];
+
+    /// The model with the given USB vendor and product id, if supported.
+    pub fn from_ids(vid: u16, pid: u16) -> Option<Self> {
+        if vid != SONY_VENDOR_ID {
+            return None;
+        }
+        ControllerModel::ALL
+            .into_iter()
+            .find(|model| model.product_ids().contains(&pid))
+    }
+
+    /// USB product ids of the model; the DualShock 4 had two revisions.
+    pub fn product_ids(self) -> &'static [u16] {
+        match self {
+            ControllerModel::DualSense => &[DUALSENSE_PRODUCT_ID],
+            ControllerModel::DualSenseEdge => &[DUALSENSE_EDGE_PRODUCT_ID],
+            ControllerModel::DualShock4 => &[DUALSHOCK4_PRODUCT_ID, DUALSHOCK4_V2_PRODUCT_ID],
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ControllerModel::DualSense => "DualSense",
+            ControllerModel::DualSenseEdge => "DualSense Edge",
+            ControllerModel::DualShock4 => "DualShock 4",
+        }
+    }
+}
+
+/// How a discovered controller can be reached.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TransportKind {
+    /// Through libusb, see [`UsbTransport`].
+    Usb,
+    /// Through a Linux hidraw node, see `HidrawTransport`.
+    Hidraw,
+}
+
+/// A controller found by [`discover`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DiscoveredDevice {
+    pub model: ControllerModel,
+    pub vendor_id: u16,
+    pub product_id: u16,
+    pub transport: TransportKind,
+    pub bus: Bus,
+    /// The controller's MAC address, as [`DeviceInfo::serial`] formats it,
+    /// or the USB serial number string of a controller that has one. `None`
+    /// if neither could be read.
+    ///
+    /// [`DeviceInfo::serial`]: crate::DeviceInfo::serial
+    pub serial: Option<String>,
+    /// Bus and port path such as `1-3.2` for libusb, the device node such as
+    /// `/dev/hidraw3` for hidraw.
+    pub path: String,
+    /// Whether the device could be opened with the current permissions.
+    pub openable: bool,
+    /// Tells apart devices attached one after the other at the same `path`:
+    /// the device address for libusb, the inode of the device node for
+    /// hidraw.
+    pub instance: u64,
+}
+
+/// Identifies a discovered device for as long as it stays connected. A
+/// controller unplugged and plugged back in gets a new id, even on the same
+/// port.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DeviceId {
+    pub transport: TransportKind,
+    pub path: String,
+    pub instance: u64,
+}
+
+impl DiscoveredDevice {
+    pub fn id(&self) -> DeviceId {
+        DeviceId {
+            transport: self.transport,
+            path: self.path.clone(),
+            instance: self.instance,
+        }
+    }
+
+    /// Reads the controller's firmware and pairing information without
+    /// taking it from the kernel driver.
+    ///
+    /// Through libusb this fails while a driver is bound to the controller,
+    /// and DualShock 4 controllers are not supported.
+    pub fn read_info(&self) -> Result<DeviceInfo> {
+        if self.model == ControllerModel::DualShock4 {
+            return Err(Error::UnsupportedReport(FEATURE_REPORT_FIRMWARE_INFO));
+        }
+        match self.transport {
+            TransportKind::Usb => {
+                let context = Context::new()?;
+                let device = self.find_usb(&context)?;
+                let device_desc = device.device_descriptor()?;
+                let mut handle = device.open()?;
+                read_usb_info(&device, &device_desc, &mut handle)
+            }
+            TransportKind::Hidraw => DualSense::new(self.open()?).info(),
+        }
+    }
+
+    /// Opens the transport this device was found on.
+    pub fn open(&self) -> Result<Box<dyn Transport>> {
+        let not_found = Error::DeviceNotFound {
+            vid: self.vendor_id,
+            pid: self.product_id,
+        };
+        match self.transport {
+            TransportKind::Usb => {
+                let context = Context::new()?;
+                let device = self.find_usb(&context)?;
+                let device_desc = device.device_descriptor()?;
+                let handle = device.open()?;
+                Ok(Box::new(UsbTransport::from_device(
+                    device,
+                    device_desc,
+                    handle,
+                )?))
+            }
+            #[cfg(target_os = "linux")]
+            TransportKind::Hidraw => {
+                let device = hidraw::list_devices()?
+                    .into_iter()
+                    .find(|device| device.path.to_string_lossy() == self.path)
+                    .ok_or(not_found)?;
+                Ok(Box::new(HidrawTransport::open_device(device)?))
+            }
+            #[cfg(not(target_os = "linux"))]
+            TransportKind::Hidraw => Err(not_found),
+        }
+    }
+
+    fn find_usb(&self, context: &Context) -> Result<Device<Context>> {
+        context
+            .devices()?
+            .iter()
+            .find(|device| {
+                usb_path(device) == self.path && device.address() as u64 == self.instance
+            })
+            .ok_or(Error::DeviceNotFound {
+                vid: self.vendor_id,
+                pid: self.product_id,
+            })
+    }
+}
+
+/// Lists every supported controller on every transport, libusb devices
+/// first.
+///
+/// Failing to initialize libusb is an error; a system without hidraw simply
+/// has no hidraw devices.
+pub fn discover() -> Result<Vec<DiscoveredDevice>> {
+    let mut devices = discover_usb(&Context::new()?)?;
+    #[cfg(target_os = "linux")]
+    {
+        let nodes = hidraw::list_devices().unwrap_or_default();
+        fill_usb_serials(&mut devices, &nodes);
+        devices.extend(hidraw_devices(&nodes));
+    }
+    Ok(devices)
+}
+
+/// Lists the supported controllers on a libusb context.
+///
+/// A controller whose interface is bound to a kernel driver is left alone,
+/// so its serial stays `None`; [`discover`] fills it in from hidraw.
+pub fn discover_usb<T: UsbContext>(context: &T) -> Result<Vec<DiscoveredDevice>> {
+    let mut devices = Vec::new();
+    for device in context.devices()?.iter() {
+        let device_desc = match device.device_descriptor() {
+            Ok(d) => d,
+            Err(_) => continue,
+        };
+        let (vid, pid) = (device_desc.vendor_id(), device_desc.product_id());
+        let model = match ControllerModel::from_ids(vid, pid) {
+            Some(model) => model,
+            None => continue,
+        };
+
+        let mut handle = device.open().ok();
+        let serial = handle.as_mut().and_then(|handle| {
+            read_serial_string(handle, &device_desc).or_else(|| {
+                // The DualShock 4 keeps its MAC address in another report.
+                (model != ControllerModel::DualShock4)
+                    .then(|| read_usb_info(&device, &device_desc, handle).ok())
+                    .flatten()
+                    .map(|info| info.serial())
+            })
+        });
+        devices.push(DiscoveredDevice {
+            model,
+            vendor_id: vid,
+            product_id: pid,
+            transport: TransportKind::Usb,
+            bus: Bus::Usb,
+            serial,
+            path: usb_path(&device),
+            openable: handle.is_some(),
+            instance: device.address() as u64,
+        });
+    }
+    Ok(devices)
+}
+
+/// Lists the supported controllers among the hidraw nodes.
+#[cfg(target_os = "linux")]
+pub fn discover_hidraw() -> Vec<DiscoveredDevice> {
+    hidraw_devices(&hidraw::list_devices().unwrap_or_default())
+}
+
+/// Picks the supported controllers out of `nodes`.
+#[cfg(target_os = "linux")]
+pub fn hidraw_devices(nodes: &[HidrawDevice]) -> Vec<DiscoveredDevice> {
+    nodes
+        .iter()
+        .filter_map(|node| {
+            let model = ControllerModel::from_ids(node.vendor_id, node.product_id)?;
+            let openable = fs::OpenOptions::new()
+                .read(true)
+                .write(true)
+                .open(&node.path)
+                .is_ok();
+            // The node is created anew whenever a device is attached.
+            let instance = fs::metadata(&node.path).map_or(0, |meta| meta.ino());
+            Some(DiscoveredDevice {
+                model,
+                vendor_id: node.vendor_id,
+                product_id: node.product_id,
+                transport: TransportKind::Hidraw,
+                bus: node.bus,
+                serial: Some(node.uniq.clone()).filter(|uniq| !uniq.is_empty()),
+                path: node.path.to_string_lossy().into_owned(),
+                openable,
+                instance,
+            })
+        })
+        .collect()
+}
+
+/// Gives libusb entries without a serial the `HID_UNIQ` of the hidraw node
+/// on the same USB port, which the Linux driver sets to the controller's
+/// MAC address.
+///
+/// `HID_PHYS` names the host controller rather than the libusb bus number,
+/// so the port chain and ids must match exactly one node.
+#[cfg(target_os = "linux")]
+pub fn fill_usb_serials(devices: &mut [DiscoveredDevice], nodes: &[HidrawDevice]) {
+    for device in devices {
+        if device.transport != TransportKind::Usb || device.serial.is_some() {
+            continue;
+        }
+        let ports = device.path.split_once('-').map(|(_, ports)| ports);
+        let mut matching = nodes.iter().filter(|node| {
+            node.bus == Bus::Usb
+                && node.vendor_id == device.vendor_id
+                && node.product_id == device.product_id
+                && !node.uniq.is_empty()
+                && phys_ports(&node.phys).is_some()
+                && phys_ports(&node.phys) == ports
+        });
+        if let (Some(node), None) = (matching.next(), matching.next()) {
+            device.serial = Some(node.uniq.clone());
+        }
+    }
+}
+
+/// Port chain of a USB `HID_PHYS` such as `usb-0000:00:14.0-3.2/input3`.
+#[cfg(target_os = "linux")]
+fn phys_ports(phys: &str) -> Option<&str> {
+    let device = phys.strip_prefix("usb-")?.split('/').next()?;
+    device.rsplit_once('-').map(|(_, ports)| ports)
+}
+
+fn read_serial_string<T: UsbContext>(
+    handle: &DeviceHandle<T>,
+    device_desc: &DeviceDescriptor,
+) -> Option<String> {
+    let language = *handle.read_languages(TRANSFER_TIMEOUT).ok()?.first()?;
+    handle
+        .read_serial_number_string(language, device_desc, TRANSFER_TIMEOUT)
+        .ok()
+        .filter(|serial| !serial.is_empty())
+}
+
+/// Reads feature reports 0x20 and 0x09, unless a kernel driver is bound to
+/// the HID interface: detaching it would take the controller away from
+/// whoever is using it.
+fn read_usb_info<T: UsbContext>(
+    device: &Device<T>,
+    device_desc: &DeviceDescriptor,
+    handle: &mut DeviceHandle<T>,
+) -> Result<DeviceInfo> {
+    let endpoint = usb::find_endpoint(
+        &mut device.clone(),
+        device_desc,
+        Direction::In,
+        TransferType::Interrupt,
+    )
+    .ok_or(Error::EndpointNotFound)?;
+    if handle.kernel_driver_active(endpoint.iface).unwrap_or(false) {
+        return Err(Error::Usb(rusb::Error::Busy));
+    }
+    handle.claim_interface(endpoint.iface)?;
+    let info = read_usb_features(handle, endpoint.iface);
+    handle.release_interface(endpoint.iface).ok();
+    info
+}
+
+fn read_usb_features<T: UsbContext>(handle: &DeviceHandle<T>, iface: u8) -> Result<DeviceInfo> {
+    let mut buf = [0; FIRMWARE_INFO_SIZE];
+    let len = usb::get_feature_report(
+        handle,
+        iface,
+        FEATURE_REPORT_FIRMWARE_INFO,
+        &mut buf,
+        TRANSFER_TIMEOUT,
+    )?;
+    let firmware = FirmwareInfo::parse(&buf[..len], Bus::Usb)?;
+
+    let mut buf = [0; PAIRING_INFO_SIZE];
+    let len = usb::get_feature_report(
+        handle,
+        iface,
+        FEATURE_REPORT_PAIRING_INFO,
+        &mut buf,
+        TRANSFER_TIMEOUT,
+    )?;
+    let pairing = PairingInfo::parse(&buf[..len], Bus::Usb)?;
+
+    Ok(DeviceInfo { firmware, pairing })
+}
+
+/// Bus number and port chain as sysfs names USB devices, e.g. `1-3.2`.
+fn usb_path<T: UsbContext>(device: &Device<T>) -> String {
+    let ports = device.port_numbers().unwrap_or_default();
+    let ports: Vec<_> = ports.iter().map(|p| p.to_string()).collect();
+    format!("{}-{}", device.bus_number(), ports.join("."))
+}
@@ -0,0 +1,95 @@
+use std::fmt;
+
+/// Errors returned by the DualSense library.
+#[derive(Debug)]
+pub enum Error {
+    /// An error reported by libusb.
+    Usb(rusb::Error),
+    /// An error from the operating system, e.g. on a hidraw node.
+    Io(std::io::Error),
+    /// No device with the given vendor and product id is connected.
+    DeviceNotFound { vid: u16, pid: u16 },
+    /// No controller is connected in the given player slot.
+    NoController { player: u8 },
+    /// The device has no interrupt endpoint in the requested direction.
+    EndpointNotFound,
+    /// No report arrived before the timeout expired.
+    Timeout,
+    /// The device does not answer to the requested report id.
+    UnsupportedReport(u8),
+    /// A report was shorter than its layout requires.
+    ReportTooShort { expected: usize, actual: usize },
+    /// A report started with an id the parser does not handle.
+    UnexpectedReportId(u8),
+    /// A Bluetooth report failed its checksum.
+    CrcMismatch { expected: u32, actual: u32 },
+    /// A capture file is malformed, or a report does not fit the format.
+    InvalidCapture(&'static str),
+    /// A button mapping file could not be parsed or is inconsistent.
+    InvalidMapping(String),
+    /// A trigger effect parameter is outside the range the controller
+    /// accepts.
+    InvalidTriggerEffect(&'static str),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Usb(e) => write!(f, "usb error: {}", e),
+            Error::Io(e) => write!(f, "io error: {}", e),
+            Error::DeviceNotFound { vid, pid } => {
+                write!(f, "could not find device {:04x}:{:04x}", vid, pid)
+            }
+            Error::NoController { player } => write!(f, "no controller for player {}", player),
+            Error::EndpointNotFound => write!(f, "no interrupt endpoint found"),
+            Error::Timeout => write!(f, "timed out"),
+            Error::UnsupportedReport(id) => write!(f, "report {:#04x} is not supported", id),
+            Error::ReportTooShort { expected, actual } => write!(
+                f,
+                "report too short: expected {} bytes, got {}",
+                expected, actual
+            ),
+            Error::UnexpectedReportId(id) => write!(f, "unexpected report id {:#04x}", id),
+            Error::CrcMismatch { expected, actual } => write!(
+                f,
+                "crc mismatch: report says {:#010x}, computed {:#010x}",
+                expected, actual
+            ),
+            Error::InvalidCapture(reason) => write!(f, "invalid capture: {}", reason),
+            Error::InvalidMapping(reason) => write!(f, "invalid mapping: {}", reason),
+            Error::InvalidTriggerEffect(reason) => {
+                write!(f, "invalid trigger effect: {}", reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Usb(e) => Some(e),
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<rusb::Error> for Error {
+    fn from(e: rusb::Error) -> Self {
+        match e {
+            rusb::Error::Timeout => Error::Timeout,
+            e => Error::Usb(e),
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        match e.kind() {
+            std::io::ErrorKind::TimedOut => Error::Timeout,
+            _ => Error::Io(e),
+        }
+    }
+}
@@ -0,0 +1,282 @@
+//! Edge events derived from consecutive input states.
+//!
+//! [`EventGenerator`] compares each [`InputState`] with the previous one and
+//! reports what changed. Time is taken from the reports' sensor clock, so the
+//! same reports always produce the same events, live or replayed.
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use crate::device::DualSense;
+use crate::input::{Button, ChargingState, InputState, PeripheralStatus, PowerStatus};
+use crate::transport::Transport;
+use crate::Result;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Axis {
+    LeftX,
+    LeftY,
+    RightX,
+    RightY,
+    L2,
+    R2,
+}
+
+impl Axis {
+    pub const ALL: [Axis; 6] = [
+        Axis::LeftX,
+        Axis::LeftY,
+        Axis::RightX,
+        Axis::RightY,
+        Axis::L2,
+        Axis::R2,
+    ];
+
+    pub fn value(self, state: &InputState) -> u8 {
+        match self {
+            Axis::LeftX => state.left_stick.x,
+            Axis::LeftY => state.left_stick.y,
+            Axis::RightX => state.right_stick.x,
+            Axis::RightY => state.right_stick.y,
+            Axis::L2 => state.l2,
+            Axis::R2 => state.r2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    ButtonPressed(Button),
+    ButtonReleased(Button),
+    /// The button has been held for the given time.
+    ButtonHeld(Button, Duration),
+    /// The axis moved by at least its threshold since it was last reported.
+    AxisChanged {
+        axis: Axis,
+        value: u8,
+        previous: u8,
+    },
+    /// Battery level or charging state changed.
+    PowerChanged {
+        status: PowerStatus,
+        previous: PowerStatus,
+    },
+    /// Headphones or a microphone were plugged in or out, or the microphone
+    /// was muted or unmuted.
+    PeripheralsChanged {
+        status: PeripheralStatus,
+        previous: PeripheralStatus,
+    },
+    /// The battery dropped to [`EventConfig::low_battery`] percent or below
+    /// while discharging. Sent again after the battery was above it or
+    /// plugged in.
+    BatteryLow(u8),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventConfig {
+    /// How long a button must be down before the first `ButtonHeld`.
+    pub hold_threshold: Duration,
+    /// Interval of further `ButtonHeld` events, `None` to send only one.
+    pub hold_repeat: Option<Duration>,
+    /// Minimum change of each axis, in [`Axis::ALL`] order, before an
+    /// `AxisChanged` is sent.
+    pub axis_thresholds: [u8; 6],
+    /// Battery percentage at which `BatteryLow` is sent, 0 to never send it.
+    pub low_battery: u8,
+}
+
+impl Default for EventConfig {
+    fn default() -> Self {
+        EventConfig {
+            hold_threshold: Duration::from_millis(500),
+            hold_repeat: None,
+            axis_thresholds: [8; 6],
+            low_battery: 15,
+        }
+    }
+}
+
+impl EventConfig {
+    pub fn axis_threshold(&self, axis: Axis) -> u8 {
+        self.axis_thresholds[axis as usize]
+    }
+
+    pub fn set_axis_threshold(&mut self, axis: Axis, threshold: u8) {
+        self.axis_thresholds[axis as usize] = threshold;
+    }
+}
+
+/// Turns a stream of input states into [`Event`]s.
+#[derive(Debug, Clone)]
+pub struct EventGenerator {
+    config: EventConfig,
+    previous: Option<InputState>,
+    now: Duration,
+    pressed_at: [Option<Duration>; Button::ALL.len()],
+    next_held: [Option<Duration>; Button::ALL.len()],
+    axes: [u8; 6],
+    battery_low: bool,
+}
+
+impl Default for EventGenerator {
+    fn default() -> Self {
+        Self::new(EventConfig::default())
+    }
+}
+
+impl EventGenerator {
+    pub fn new(config: EventConfig) -> Self {
+        EventGenerator {
+            config,
+            previous: None,
+            now: Duration::ZERO,
+            pressed_at: [None; Button::ALL.len()],
+            next_held: [None; Button::ALL.len()],
+            axes: [0; 6],
+            battery_low: false,
+        }
+    }
+
+    pub fn config(&self) -> &EventConfig {
+        &self.config
+    }
+
+    pub fn config_mut(&mut self) -> &mut EventConfig {
+        &mut self.config
+    }
+
+    /// Time elapsed on the sensor clock since the first state.
+    pub fn now(&self) -> Duration {
+        self.now
+    }
+
+    /// Compares `state` with the previous one and returns the events, in the
+    /// order releases, presses, holds, axis changes, power and peripheral
+    /// changes, low battery.
+    ///
+    /// The first state only sets the baseline for axes and status; buttons
+    /// already down are reported as pressed and a battery already low is
+    /// reported.
+    pub fn update(&mut self, state: &InputState) -> Vec<Event> {
+        let mut events = Vec::new();
+
+        let previous = match self.previous {
+            Some(previous) => {
+                let ticks = state
+                    .sensor_timestamp
+                    .wrapping_sub(previous.sensor_timestamp);
+                self.now += Duration::from_micros(ticks as u64 / 3);
+                previous
+            }
+            None => {
+                for axis in Axis::ALL {
+                    self.axes[axis as usize] = axis.value(state);
+                }
+                InputState {
+                    power: state.power,
+                    peripherals: state.peripherals,
+                    ..Default::default()
+                }
+            }
+        };
+
+        for (i, button) in Button::ALL.into_iter().enumerate() {
+            if previous.is_pressed(button) && !state.is_pressed(button) {
+                self.pressed_at[i] = None;
+                self.next_held[i] = None;
+                events.push(Event::ButtonReleased(button));
+            }
+        }
+        for (i, button) in Button::ALL.into_iter().enumerate() {
+            if !previous.is_pressed(button) && state.is_pressed(button) {
+                self.pressed_at[i] = Some(self.now);
+                self.next_held[i] = Some(self.now + self.config.hold_threshold);
+                events.push(Event::ButtonPressed(button));
+            }
+        }
+        for (i, button) in Button::ALL.into_iter().enumerate() {
+            if let (Some(pressed_at), Some(next_held)) = (self.pressed_at[i], self.next_held[i]) {
+                if self.now >= next_held {
+                    events.push(Event::ButtonHeld(button, self.now - pressed_at));
+                    self.next_held[i] = self.config.hold_repeat.map(|r| next_held + r);
+                }
+            }
+        }
+        for axis in Axis::ALL {
+            let value = axis.value(state);
+            let reported = self.axes[axis as usize];
+            if value.abs_diff(reported) >= self.config.axis_threshold(axis).max(1) {
+                self.axes[axis as usize] = value;
+                events.push(Event::AxisChanged {
+                    axis,
+                    value,
+                    previous: reported,
+                });
+            }
+        }
+
+        if state.power != previous.power {
+            events.push(Event::PowerChanged {
+                status: state.power,
+                previous: previous.power,
+            });
+        }
+        if state.peripherals != previous.peripherals {
+            events.push(Event::PeripheralsChanged {
+                status: state.peripherals,
+                previous: previous.peripherals,
+            });
+        }
+        let percentage = state.power.percentage();
+        let low = state.power.charging == ChargingState::Discharging
+            && percentage <= self.config.low_battery;
+        if low && !self.battery_low {
+            events.push(Event::BatteryLow(percentage));
+        }
+        self.battery_low = low;
+
+        self.previous = Some(*state);
+        events
+    }
+}
+
+/// Iterator over the events of a controller, reading reports as needed.
+pub struct Events<'a, T: Transport> {
+    dualsense: &'a mut DualSense<T>,
+    generator: EventGenerator,
+    pending: VecDeque<Event>,
+    timeout: Duration,
+}
+
+impl<'a, T: Transport> Events<'a, T> {
+    pub fn new(dualsense: &'a mut DualSense<T>, generator: EventGenerator) -> Self {
+        Events {
+            dualsense,
+            generator,
+            pending: VecDeque::new(),
+            timeout: Duration::from_secs(1),
+        }
+    }
+
+    /// Timeout for each report read.
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+}
+
+impl<T: Transport> Iterator for Events<'_, T> {
+    type Item = Result<Event>;
+
+    /// Never ends; read errors are passed through and reading continues on
+    /// the next call.
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.pending.is_empty() {
+            match self.dualsense.read_state(self.timeout) {
+                Ok(state) => self.pending.extend(self.generator.update(&state)),
+                Err(e) => return Some(Err(e)),
+            }
+        }
+        self.pending.pop_front().map(Ok)
+    }
+}
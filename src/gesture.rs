@@ -0,0 +1,399 @@
+//! Touchpad gestures.
+//!
+//! [`GestureRecognizer`] follows the fingers on the touchpad across input
+//! states and recognizes taps, double taps, long presses, swipes, pinches
+//! and two-finger scrolling. Like [`EventGenerator`](crate::EventGenerator)
+//! it takes time from the reports' sensor clock, so recorded reports always
+//! produce the same gestures.
+
+use std::time::Duration;
+
+use crate::input::InputState;
+use crate::touch::{FingerTracker, TouchEvent};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SwipeDirection {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+impl SwipeDirection {
+    /// Dominant direction of a movement, y growing downwards.
+    fn of(dx: i32, dy: i32) -> Self {
+        if dx.abs() >= dy.abs() {
+            if dx < 0 {
+                SwipeDirection::Left
+            } else {
+                SwipeDirection::Right
+            }
+        } else if dy < 0 {
+            SwipeDirection::Up
+        } else {
+            SwipeDirection::Down
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Gesture {
+    /// A short touch of one finger that did not move. Sent once it is clear
+    /// that no second tap follows.
+    Tap { x: u16, y: u16 },
+    /// Two taps in quick succession, at the position of the second one.
+    DoubleTap { x: u16, y: u16 },
+    /// One finger held still; sent while it is still down.
+    LongPress { x: u16, y: u16 },
+    /// A quick stroke of one or two fingers, sent when the last one lifts.
+    Swipe {
+        fingers: u8,
+        direction: SwipeDirection,
+    },
+    /// Two fingers moving apart or together. `scale` is their distance
+    /// relative to when the second finger touched.
+    Pinch { scale: f32 },
+    /// Two fingers moving in the same direction, by the given distance since
+    /// the previous `Scroll`.
+    Scroll { dx: i32, dy: i32 },
+}
+
+/// Thresholds of the [`GestureRecognizer`]. Distances are in touchpad units,
+/// see [`TOUCHPAD_WIDTH`](crate::input::TOUCHPAD_WIDTH).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GestureConfig {
+    /// Longest touch that still counts as a tap.
+    pub tap_duration: Duration,
+    /// How far a finger may move and still tap or long press.
+    pub tap_slop: u16,
+    /// Longest time between lifting after a tap and touching again for a
+    /// double tap. Zero disables double taps and sends taps right away.
+    pub double_tap_interval: Duration,
+    /// How far apart the two taps of a double tap may be.
+    pub double_tap_distance: u16,
+    pub long_press_duration: Duration,
+    /// Shortest distance of a swipe.
+    pub swipe_distance: u16,
+    /// Longest duration of a swipe, from the first touch to the last lift.
+    pub swipe_duration: Duration,
+    /// Change of the distance between two fingers that starts a pinch.
+    pub pinch_threshold: u16,
+    /// Distance two fingers must move together to start scrolling.
+    pub scroll_threshold: u16,
+}
+
+impl Default for GestureConfig {
+    fn default() -> Self {
+        GestureConfig {
+            tap_duration: Duration::from_millis(250),
+            tap_slop: 40,
+            double_tap_interval: Duration::from_millis(300),
+            double_tap_distance: 150,
+            long_press_duration: Duration::from_millis(600),
+            swipe_distance: 300,
+            swipe_duration: Duration::from_millis(500),
+            pinch_threshold: 80,
+            scroll_threshold: 40,
+        }
+    }
+}
+
+type Point = (i32, i32);
+
+fn distance(a: Point, b: Point) -> f32 {
+    ((a.0 - b.0) as f32).hypot((a.1 - b.1) as f32)
+}
+
+#[derive(Debug, Clone, Copy)]
+enum TwoFingerMode {
+    Undecided,
+    Pinch { scale: f32 },
+    Scroll { last: Point },
+}
+
+/// Where two fingers were when the second one touched.
+#[derive(Debug, Clone, Copy)]
+struct TwoFinger {
+    distance: f32,
+    center: Point,
+    mode: TwoFingerMode,
+}
+
+/// Everything from the first finger touching to the last one lifting.
+#[derive(Debug, Clone)]
+struct Session {
+    started: Duration,
+    /// Id, first and last position of every finger that touched.
+    fingers: Vec<(u32, Point, Point)>,
+    max_fingers: u8,
+    moved: bool,
+    long_pressed: bool,
+    pinched: bool,
+    second_tap: bool,
+    two_finger: Option<TwoFinger>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct PendingTap {
+    lifted: Duration,
+    x: u16,
+    y: u16,
+}
+
+/// Turns a stream of input states into touchpad [`Gesture`]s.
+#[derive(Debug, Clone)]
+pub struct GestureRecognizer {
+    config: GestureConfig,
+    tracker: FingerTracker,
+    last_timestamp: Option<u32>,
+    now: Duration,
+    session: Option<Session>,
+    pending_tap: Option<PendingTap>,
+}
+
+impl Default for GestureRecognizer {
+    fn default() -> Self {
+        Self::new(GestureConfig::default())
+    }
+}
+
+impl GestureRecognizer {
+    pub fn new(config: GestureConfig) -> Self {
+        GestureRecognizer {
+            config,
+            tracker: FingerTracker::new(),
+            last_timestamp: None,
+            now: Duration::ZERO,
+            session: None,
+            pending_tap: None,
+        }
+    }
+
+    pub fn config(&self) -> &GestureConfig {
+        &self.config
+    }
+
+    pub fn config_mut(&mut self) -> &mut GestureConfig {
+        &mut self.config
+    }
+
+    /// Time elapsed on the sensor clock since the first state.
+    pub fn now(&self) -> Duration {
+        self.now
+    }
+
+    /// Follows the touchpad contacts of `state` and returns the gestures
+    /// recognized so far.
+    ///
+    /// A quick two-finger swipe usually also scrolls before the swipe is
+    /// sent.
+    pub fn update(&mut self, state: &InputState) -> Vec<Gesture> {
+        if let Some(last) = self.last_timestamp {
+            let ticks = state.sensor_timestamp.wrapping_sub(last);
+            self.now += Duration::from_micros(ticks as u64 / 3);
+        }
+        self.last_timestamp = Some(state.sensor_timestamp);
+
+        let mut gestures = Vec::new();
+        for event in self.tracker.update(state) {
+            match event {
+                TouchEvent::Down(finger) => {
+                    self.touch(finger.id, finger.x, finger.y, &mut gestures)
+                }
+                TouchEvent::Move { finger, .. } | TouchEvent::Up(finger) => {
+                    let slop = self.config.tap_slop as f32;
+                    let Some(session) = &mut self.session else {
+                        continue;
+                    };
+                    for (id, start, last) in &mut session.fingers {
+                        if *id == finger.id {
+                            *last = (finger.x as i32, finger.y as i32);
+                            session.moved |= distance(*start, *last) > slop;
+                        }
+                    }
+                }
+            }
+        }
+
+        if self.tracker.fingers().len() < 2 {
+            if let Some(session) = &mut self.session {
+                session.two_finger = None;
+            }
+        }
+        self.two_fingers(&mut gestures);
+        self.long_press(&mut gestures);
+
+        if let Some(session) = &self.session {
+            let too_late = session.moved
+                || session.max_fingers > 1
+                || self.now - session.started > self.config.tap_duration;
+            if too_late {
+                self.flush_tap(&mut gestures);
+            }
+        }
+        if self.tracker.fingers().is_empty() {
+            if let Some(session) = self.session.take() {
+                self.finish(session, &mut gestures);
+            }
+        }
+        if let Some(tap) = self.pending_tap {
+            if self.session.is_none() && self.now - tap.lifted > self.config.double_tap_interval {
+                self.flush_tap(&mut gestures);
+            }
+        }
+        gestures
+    }
+
+    /// Forgets the fingers down and any tap waiting for a second one.
+    pub fn reset(&mut self) {
+        self.tracker.reset();
+        self.session = None;
+        self.pending_tap = None;
+    }
+
+    fn touch(&mut self, id: u32, x: u16, y: u16, gestures: &mut Vec<Gesture>) {
+        let position = (x as i32, y as i32);
+        if self.session.is_none() {
+            let second_tap = self.pending_tap.is_some_and(|tap| {
+                self.now - tap.lifted <= self.config.double_tap_interval
+                    && distance((tap.x as i32, tap.y as i32), position)
+                        <= self.config.double_tap_distance as f32
+            });
+            if !second_tap {
+                self.flush_tap(gestures);
+            }
+            self.session = Some(Session {
+                started: self.now,
+                fingers: Vec::new(),
+                max_fingers: 0,
+                moved: false,
+                long_pressed: false,
+                pinched: false,
+                second_tap,
+                two_finger: None,
+            });
+        }
+
+        let fingers = self.tracker.fingers();
+        let session = self.session.as_mut().expect("session was just started");
+        session.fingers.push((id, position, position));
+        session.max_fingers = session.max_fingers.max(fingers.len() as u8);
+        if let [a, b] = fingers {
+            let (a, b) = ((a.x as i32, a.y as i32), (b.x as i32, b.y as i32));
+            session.two_finger = Some(TwoFinger {
+                distance: distance(a, b),
+                center: ((a.0 + b.0) / 2, (a.1 + b.1) / 2),
+                mode: TwoFingerMode::Undecided,
+            });
+        }
+    }
+
+    fn two_fingers(&mut self, gestures: &mut Vec<Gesture>) {
+        let (Some(session), [a, b]) = (&mut self.session, self.tracker.fingers()) else {
+            return;
+        };
+        let Some(two) = &mut session.two_finger else {
+            return;
+        };
+        let (a, b) = ((a.x as i32, a.y as i32), (b.x as i32, b.y as i32));
+        let spread = distance(a, b);
+        let center = ((a.0 + b.0) / 2, (a.1 + b.1) / 2);
+
+        if let TwoFingerMode::Undecided = two.mode {
+            if (spread - two.distance).abs() >= self.config.pinch_threshold as f32 {
+                two.mode = TwoFingerMode::Pinch { scale: 1.0 };
+                session.pinched = true;
+            } else if distance(center, two.center) >= self.config.scroll_threshold as f32 {
+                two.mode = TwoFingerMode::Scroll { last: two.center };
+            }
+        }
+        match &mut two.mode {
+            TwoFingerMode::Undecided => {}
+            TwoFingerMode::Pinch { scale } => {
+                let current = spread / two.distance.max(1.0);
+                if current != *scale {
+                    *scale = current;
+                    gestures.push(Gesture::Pinch { scale: current });
+                }
+            }
+            TwoFingerMode::Scroll { last } => {
+                let (dx, dy) = (center.0 - last.0, center.1 - last.1);
+                if (dx, dy) != (0, 0) {
+                    *last = center;
+                    gestures.push(Gesture::Scroll { dx, dy });
+                }
+            }
+        }
+    }
+
+    fn long_press(&mut self, gestures: &mut Vec<Gesture>) {
+        let Some(session) = &self.session else {
+            return;
+        };
+        if session.max_fingers != 1
+            || session.moved
+            || session.long_pressed
+            || self.now - session.started < self.config.long_press_duration
+        {
+            return;
+        }
+        let (_, _, (x, y)) = session.fingers[0];
+        self.flush_tap(gestures);
+        gestures.push(Gesture::LongPress {
+            x: x as u16,
+            y: y as u16,
+        });
+        if let Some(session) = &mut self.session {
+            session.long_pressed = true;
+        }
+    }
+
+    fn finish(&mut self, session: Session, gestures: &mut Vec<Gesture>) {
+        let duration = self.now - session.started;
+        if session.long_pressed || session.pinched {
+            return;
+        }
+
+        if session.max_fingers == 1 && !session.moved && duration <= self.config.tap_duration {
+            let (_, _, (x, y)) = session.fingers[0];
+            let (x, y) = (x as u16, y as u16);
+            if session.second_tap && self.pending_tap.take().is_some() {
+                gestures.push(Gesture::DoubleTap { x, y });
+            } else if self.config.double_tap_interval.is_zero() {
+                gestures.push(Gesture::Tap { x, y });
+            } else {
+                self.pending_tap = Some(PendingTap {
+                    lifted: self.now,
+                    x,
+                    y,
+                });
+            }
+            return;
+        }
+
+        if duration > self.config.swipe_duration {
+            return;
+        }
+        let count = session.fingers.len() as i32;
+        let (dx, dy) = session
+            .fingers
+            .iter()
+            .fold((0, 0), |(dx, dy), (_, start, last)| {
+                (dx + last.0 - start.0, dy + last.1 - start.1)
+            });
+        let (dx, dy) = (dx / count, dy / count);
+        if distance((0, 0), (dx, dy)) >= self.config.swipe_distance as f32 {
+            gestures.push(Gesture::Swipe {
+                fingers: session.max_fingers,
+                direction: SwipeDirection::of(dx, dy),
+            });
+        }
+    }
+
+    /// Sends the tap that was waiting for a possible second one.
+    fn flush_tap(&mut self, gestures: &mut Vec<Gesture>) {
+        if let Some(tap) = self.pending_tap.take() {
+            gestures.push(Gesture::Tap { x: tap.x, y: tap.y });
+        }
+    }
+}
@@ -0,0 +1,453 @@
+//! Noticing controllers being connected and disconnected.
+//!
+//! [`HotplugMonitor`] keeps the list from [`discover`] up to date and reports
+//! the difference as [`HotplugEvent`]s. It rescans when libusb reports a
+//! hotplug event, when the kernel announces a new or removed USB or hidraw
+//! device on Linux, or, where neither is available, at a fixed interval.
+//!
+//! [`ReattachingDualSense`] builds on it to keep one controller, found by
+//! serial, attached across unplugging and replugging.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use rusb::{Context, Device, Hotplug, HotplugBuilder, Registration, UsbContext};
+
+use crate::clock::{Clock, SystemClock};
+use crate::device::{DualSense, SONY_VENDOR_ID};
+use crate::discovery::{discover, DeviceId, DiscoveredDevice, TransportKind};
+use crate::info::DeviceInfo;
+use crate::transport::Transport;
+use crate::Result;
+
+/// Time given to the system to finish setting up a device before scanning,
+/// e.g. for the driver to publish the serial and udev to fix permissions.
+const SETTLE_TIME: Duration = Duration::from_millis(200);
+
+/// How long to wait on one notification source while watching two.
+const WAIT_SLICE: Duration = Duration::from_millis(50);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HotplugEvent {
+    /// A device appeared, with the controller's information if it could be
+    /// read without taking the device from its driver.
+    Connected {
+        device: DiscoveredDevice,
+        info: Option<DeviceInfo>,
+    },
+    Disconnected(DeviceId),
+}
+
+type Scanner = Box<dyn FnMut() -> Result<Vec<DiscoveredDevice>>>;
+type InfoReader = Box<dyn FnMut(&DiscoveredDevice) -> Result<DeviceInfo>>;
+
+/// Watches for controllers coming and going.
+pub struct HotplugMonitor<C: Clock = SystemClock> {
+    clock: C,
+    scan: Scanner,
+    read_info: Option<InfoReader>,
+    known: Vec<DiscoveredDevice>,
+    scanned: bool,
+    usb: Option<UsbHotplug>,
+    #[cfg(target_os = "linux")]
+    uevents: Option<uevent::Socket>,
+    poll_interval: Duration,
+    next_scan: Duration,
+}
+
+impl HotplugMonitor<SystemClock> {
+    /// Monitor over [`discover`], notified by libusb hotplug callbacks where
+    /// libusb supports them and by kernel uevents on Linux. Without either it
+    /// rescans every second. New devices are asked for their information
+    /// with [`DiscoveredDevice::read_info`].
+    ///
+    /// Fails only if libusb cannot be initialized.
+    pub fn new() -> Result<Self> {
+        let context = Context::new()?;
+        let mut monitor = Self::polling(discover, SystemClock::new());
+        monitor.set_info_reader(|device| device.read_info());
+        if rusb::has_hotplug() {
+            monitor.usb = UsbHotplug::register(context).ok();
+        }
+        #[cfg(target_os = "linux")]
+        {
+            monitor.uevents = uevent::Socket::open().ok();
+        }
+        Ok(monitor)
+    }
+}
+
+impl<C: Clock> HotplugMonitor<C> {
+    /// Monitor that calls `scan` on every poll interval of `clock`, without
+    /// any notifications and without reading device information. Useful with
+    /// simulated controllers.
+    pub fn polling<F>(scan: F, clock: C) -> Self
+    where
+        F: FnMut() -> Result<Vec<DiscoveredDevice>> + 'static,
+    {
+        let now = clock.now();
+        HotplugMonitor {
+            clock,
+            scan: Box::new(scan),
+            read_info: None,
+            known: Vec::new(),
+            scanned: false,
+            usb: None,
+            #[cfg(target_os = "linux")]
+            uevents: None,
+            poll_interval: Duration::from_secs(1),
+            next_scan: now,
+        }
+    }
+
+    /// Interval of rescans when no notifications are available.
+    pub fn set_poll_interval(&mut self, interval: Duration) {
+        self.poll_interval = interval;
+    }
+
+    /// Reads the information of each new device with `read`. A device
+    /// without a serial takes the MAC address from it.
+    pub fn set_info_reader<F>(&mut self, read: F)
+    where
+        F: FnMut(&DiscoveredDevice) -> Result<DeviceInfo> + 'static,
+    {
+        self.read_info = Some(Box::new(read));
+    }
+
+    /// Devices connected as of the last scan.
+    pub fn devices(&self) -> &[DiscoveredDevice] {
+        &self.known
+    }
+
+    /// Waits up to `timeout` for devices to change and returns what did.
+    ///
+    /// The first call returns right away, reporting every device already
+    /// connected.
+    pub fn poll(&mut self, timeout: Duration) -> Result<Vec<HotplugEvent>> {
+        if self.scanned && !self.wait(timeout)? {
+            return Ok(Vec::new());
+        }
+        self.scanned = true;
+        self.rescan()
+    }
+
+    /// Scans now and returns the changes since the last scan.
+    pub fn rescan(&mut self) -> Result<Vec<HotplugEvent>> {
+        let devices = (self.scan)()?;
+        self.next_scan = self.clock.now() + self.poll_interval;
+
+        let mut events = Vec::new();
+        for device in &self.known {
+            if !devices.iter().any(|d| d.id() == device.id()) {
+                events.push(HotplugEvent::Disconnected(device.id()));
+            }
+        }
+        let mut known = Vec::with_capacity(devices.len());
+        for mut device in devices {
+            if let Some(old) = self.known.iter().find(|d| d.id() == device.id()) {
+                if device.serial.is_none() {
+                    device.serial = old.serial.clone();
+                }
+                known.push(device);
+                continue;
+            }
+            let info = match &mut self.read_info {
+                Some(read) => read(&device).ok(),
+                None => None,
+            };
+            if device.serial.is_none() {
+                device.serial = info.as_ref().map(DeviceInfo::serial);
+            }
+            events.push(HotplugEvent::Connected {
+                device: device.clone(),
+                info,
+            });
+            known.push(device);
+        }
+        self.known = known;
+        Ok(events)
+    }
+
+    /// Waits until a rescan is due, returning false if `timeout` passes
+    /// first.
+    fn wait(&mut self, timeout: Duration) -> Result<bool> {
+        let deadline = self.clock.now() + timeout;
+        loop {
+            let now = self.clock.now();
+            if self.needs_polling() && now >= self.next_scan {
+                return Ok(true);
+            }
+            if now >= deadline {
+                return Ok(false);
+            }
+            let mut wait = deadline - now;
+            if self.needs_polling() {
+                wait = wait.min(self.next_scan - now);
+            }
+            if self.notified(wait)? {
+                self.clock.sleep(SETTLE_TIME);
+                return Ok(true);
+            }
+        }
+    }
+
+    /// Whether some device changed may go unnoticed without rescanning
+    /// regularly: Bluetooth controllers are only seen through uevents.
+    fn needs_polling(&self) -> bool {
+        #[cfg(target_os = "linux")]
+        return self.uevents.is_none();
+        #[cfg(not(target_os = "linux"))]
+        return self.usb.is_none();
+    }
+
+    /// Waits up to `timeout` for a notification from any source.
+    fn notified(&mut self, timeout: Duration) -> Result<bool> {
+        #[cfg(target_os = "linux")]
+        if let Some(uevents) = &self.uevents {
+            return match &self.usb {
+                Some(usb) => {
+                    Ok(usb.wait(timeout.min(WAIT_SLICE))?
+                        || uevents.wait(timeout.min(WAIT_SLICE))?)
+                }
+                None => Ok(uevents.wait(timeout)?),
+            };
+        }
+        match &self.usb {
+            Some(usb) => usb.wait(timeout),
+            None => {
+                self.clock.sleep(timeout);
+                Ok(false)
+            }
+        }
+    }
+}
+
+/// A libusb hotplug callback that flags every arrival and departure of a
+/// Sony device.
+struct UsbHotplug {
+    _registration: Registration<Context>,
+    context: Context,
+    changed: Arc<AtomicBool>,
+}
+
+struct Notify(Arc<AtomicBool>);
+
+impl<T: UsbContext> Hotplug<T> for Notify {
+    fn device_arrived(&mut self, _device: Device<T>) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    fn device_left(&mut self, _device: Device<T>) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+}
+
+impl UsbHotplug {
+    fn register(context: Context) -> Result<Self> {
+        let changed = Arc::new(AtomicBool::new(false));
+        let mut builder = HotplugBuilder::new();
+        builder.vendor_id(SONY_VENDOR_ID);
+        let registration = builder.register(&context, Box::new(Notify(changed.clone())))?;
+        Ok(UsbHotplug {
+            _registration: registration,
+            context,
+            changed,
+        })
+    }
+
+    fn wait(&self, timeout: Duration) -> Result<bool> {
+        self.context.handle_events(Some(timeout))?;
+        Ok(self.changed.swap(false, Ordering::SeqCst))
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod uevent {
+    use std::io;
+    use std::mem;
+    use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+    use std::time::Duration;
+
+    /// Netlink socket receiving the kernel's device uevents, the same ones
+    /// udev listens to.
+    pub struct Socket {
+        fd: OwnedFd,
+    }
+
+    impl Socket {
+        pub fn open() -> io::Result<Self> {
+            let fd = unsafe {
+                libc::socket(
+                    libc::AF_NETLINK,
+                    libc::SOCK_DGRAM | libc::SOCK_CLOEXEC | libc::SOCK_NONBLOCK,
+                    libc::NETLINK_KOBJECT_UEVENT,
+                )
+            };
+            if fd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+            let mut addr: libc::sockaddr_nl = unsafe { mem::zeroed() };
+            addr.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+            // Multicast group 1 carries the kernel's own uevents.
+            addr.nl_groups = 1;
+            let n = unsafe {
+                libc::bind(
+                    fd.as_raw_fd(),
+                    &addr as *const libc::sockaddr_nl as *const libc::sockaddr,
+                    mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+                )
+            };
+            if n < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(Socket { fd })
+        }
+
+        /// Waits up to `timeout` for uevents and returns whether any of them
+        /// was about a USB or hidraw device.
+        pub fn wait(&self, timeout: Duration) -> io::Result<bool> {
+            let mut pollfd = libc::pollfd {
+                fd: self.fd.as_raw_fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            let timeout_ms = timeout.as_millis().min(i32::MAX as u128) as i32;
+            if unsafe { libc::poll(&mut pollfd, 1, timeout_ms) } < 0 {
+                return Err(io::Error::last_os_error());
+            }
+
+            let mut relevant = false;
+            let mut buf = [0u8; 4096];
+            loop {
+                let n = unsafe {
+                    libc::recv(
+                        self.fd.as_raw_fd(),
+                        buf.as_mut_ptr() as *mut libc::c_void,
+                        buf.len(),
+                        0,
+                    )
+                };
+                if n < 0 {
+                    let e = io::Error::last_os_error();
+                    if e.kind() == io::ErrorKind::WouldBlock {
+                        return Ok(relevant);
+                    }
+                    return Err(e);
+                }
+                // "action@devpath" followed by NUL separated KEY=value pairs.
+                relevant |= buf[..n as usize]
+                    .split(|&b| b == 0)
+                    .any(|field| field == b"SUBSYSTEM=usb" || field == b"SUBSYSTEM=hidraw");
+            }
+        }
+    }
+}
+
+/// Opens the transport of a discovered device.
+type Opener = Box<dyn FnMut(&DiscoveredDevice) -> Result<Box<dyn Transport>>>;
+
+/// One controller, found by serial, that is reattached whenever it comes
+/// back after being disconnected.
+///
+/// Only devices the monitor reports with a matching serial are opened, so
+/// other controllers are never taken from their driver. When the controller
+/// is reachable in several ways hidraw is preferred, since it leaves the
+/// kernel driver in place.
+pub struct ReattachingDualSense<C: Clock = SystemClock> {
+    serial: String,
+    monitor: HotplugMonitor<C>,
+    open: Opener,
+    attached: Option<(DeviceId, DualSense<Box<dyn Transport>>)>,
+}
+
+impl<C: Clock> ReattachingDualSense<C> {
+    /// Follows the controller with `serial`, a MAC address as
+    /// [`DeviceInfo::serial`](crate::DeviceInfo::serial) formats it.
+    pub fn new(serial: &str, monitor: HotplugMonitor<C>) -> Self {
+        Self::with_opener(serial, monitor, |device| device.open())
+    }
+
+    /// Like [`ReattachingDualSense::new`], opening devices with `open`.
+    pub fn with_opener<F>(serial: &str, monitor: HotplugMonitor<C>, open: F) -> Self
+    where
+        F: FnMut(&DiscoveredDevice) -> Result<Box<dyn Transport>> + 'static,
+    {
+        ReattachingDualSense {
+            serial: serial.to_ascii_lowercase(),
+            monitor,
+            open: Box::new(open),
+            attached: None,
+        }
+    }
+
+    pub fn serial(&self) -> &str {
+        &self.serial
+    }
+
+    pub fn monitor(&self) -> &HotplugMonitor<C> {
+        &self.monitor
+    }
+
+    pub fn is_attached(&self) -> bool {
+        self.attached.is_some()
+    }
+
+    /// The device the controller is currently reached through.
+    pub fn attached_device(&self) -> Option<&DeviceId> {
+        self.attached.as_ref().map(|(id, _)| id)
+    }
+
+    pub fn dualsense(&mut self) -> Option<&mut DualSense<Box<dyn Transport>>> {
+        self.attached.as_mut().map(|(_, dualsense)| dualsense)
+    }
+
+    /// Drops the controller, e.g. after it stopped answering; it is looked
+    /// for again on the next poll.
+    pub fn detach(&mut self) {
+        self.attached = None;
+    }
+
+    /// Waits up to `timeout` for devices to change, detaches the controller
+    /// if it went away and attaches it if it is back. Returns the monitor's
+    /// events.
+    pub fn poll(&mut self, timeout: Duration) -> Result<Vec<HotplugEvent>> {
+        let events = self.monitor.poll(timeout)?;
+        for event in &events {
+            if let HotplugEvent::Disconnected(id) = event {
+                if self.attached_device() == Some(id) {
+                    self.attached = None;
+                }
+            }
+        }
+        if self.attached.is_none() {
+            self.attach();
+        }
+        Ok(events)
+    }
+
+    fn attach(&mut self) {
+        let mut candidates: Vec<_> = self
+            .monitor
+            .devices()
+            .iter()
+            .filter(|device| {
+                device
+                    .serial
+                    .as_ref()
+                    .is_some_and(|serial| serial.eq_ignore_ascii_case(&self.serial))
+            })
+            .cloned()
+            .collect();
+        candidates.sort_by_key(|device| device.transport != TransportKind::Hidraw);
+
+        for device in candidates {
+            if let Ok(transport) = (self.open)(&device) {
+                self.attached = Some((device.id(), DualSense::new(transport)));
+                return;
+            }
+        }
+    }
+}
@@ -0,0 +1,166 @@
+//! Calibration of the gyroscope and accelerometer.
+//!
+//! Feature report 0x05 holds factory calibration values for both sensors.
+//! They are turned into a per-axis bias and scale the same way the Linux
+//! `hid-playstation` driver does, so the values match what the kernel
+//! reports through evdev.
+
+use crate::crc;
+use crate::device::{Bus, FEATURE_REPORT_CALIBRATION};
+use crate::input::InputState;
+use crate::{Error, Result};
+
+pub const CALIBRATION_REPORT_SIZE: usize = 41;
+
+/// Calibrated gyro units per degree per second.
+pub const GYRO_RES_PER_DEG_S: i32 = 1024;
+/// Calibrated accelerometer units per g.
+pub const ACC_RES_PER_G: i32 = 8192;
+
+/// Full scale of the gyro, in calibrated units.
+const GYRO_RANGE: i32 = 2048 * GYRO_RES_PER_DEG_S;
+/// Full scale of the accelerometer, in calibrated units.
+const ACC_RANGE: i32 = 4 * ACC_RES_PER_G;
+
+/// Bias and scale of one sensor axis: `(raw - bias) * numer / denom`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AxisCalibration {
+    pub bias: i32,
+    pub numer: i32,
+    pub denom: i32,
+}
+
+impl AxisCalibration {
+    /// Passes raw values through unchanged.
+    pub const IDENTITY: AxisCalibration = AxisCalibration {
+        bias: 0,
+        numer: 1,
+        denom: 1,
+    };
+
+    /// Applies the calibration with the kernel's integer arithmetic.
+    pub fn apply(&self, raw: i16) -> i32 {
+        // mult_frac() in the kernel; truncating the full product gives the
+        // same result since the numerator is never negative.
+        (self.numer as i64 * (raw as i32 - self.bias) as i64 / self.denom as i64) as i32
+    }
+}
+
+impl Default for AxisCalibration {
+    fn default() -> Self {
+        AxisCalibration::IDENTITY
+    }
+}
+
+/// Factory calibration of both motion sensors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct ImuCalibration {
+    /// Pitch, yaw and roll, calibrated to [`GYRO_RES_PER_DEG_S`].
+    pub gyro: [AxisCalibration; 3],
+    /// X, y and z, calibrated to [`ACC_RES_PER_G`].
+    pub accel: [AxisCalibration; 3],
+}
+
+impl ImuCalibration {
+    /// Parses feature report 0x05, report id included. When read over
+    /// Bluetooth the report ends with a checksum, which is verified.
+    pub fn parse(report: &[u8], bus: Bus) -> Result<Self> {
+        if report.len() < CALIBRATION_REPORT_SIZE {
+            return Err(Error::ReportTooShort {
+                expected: CALIBRATION_REPORT_SIZE,
+                actual: report.len(),
+            });
+        }
+        if report[0] != FEATURE_REPORT_CALIBRATION {
+            return Err(Error::UnexpectedReportId(report[0]));
+        }
+        let report = &report[..CALIBRATION_REPORT_SIZE];
+        if bus == Bus::Bluetooth {
+            crc::verify(crc::SEED_FEATURE, report)
+                .map_err(|(expected, actual)| Error::CrcMismatch { expected, actual })?;
+        }
+
+        let mut values = [0; 17];
+        for (i, value) in values.iter_mut().enumerate() {
+            *value = i16::from_le_bytes([report[1 + i * 2], report[2 + i * 2]]);
+        }
+        Ok(Self::from_raw(values))
+    }
+
+    /// Builds the calibration from the 17 values of feature report 0x05: gyro
+    /// pitch, yaw and roll bias, gyro pitch, yaw and roll plus/minus, gyro
+    /// speed plus/minus, then accelerometer x, y and z plus/minus.
+    ///
+    /// Like the kernel, an axis whose values would divide by zero falls back
+    /// to mapping the full raw range onto the sensor's nominal range.
+    pub fn from_raw(values: [i16; 17]) -> Self {
+        let v = values.map(|v| v as i32);
+        let [pitch_bias, yaw_bias, roll_bias] = [v[0], v[1], v[2]];
+        let speed_2x = v[9] + v[10];
+
+        // The controller already subtracts the gyro bias itself, it only
+        // enters into the range.
+        let gyro = |plus: i32, minus: i32, bias: i32| AxisCalibration {
+            bias: 0,
+            numer: speed_2x * GYRO_RES_PER_DEG_S,
+            denom: (plus - bias).abs() + (minus - bias).abs(),
+        };
+        let accel = |plus: i32, minus: i32| {
+            let range_2g = plus - minus;
+            AxisCalibration {
+                bias: plus - range_2g / 2,
+                numer: 2 * ACC_RES_PER_G,
+                denom: range_2g,
+            }
+        };
+
+        let mut calibration = ImuCalibration {
+            gyro: [
+                gyro(v[3], v[4], pitch_bias),
+                gyro(v[5], v[6], yaw_bias),
+                gyro(v[7], v[8], roll_bias),
+            ],
+            accel: [
+                accel(v[11], v[12]),
+                accel(v[13], v[14]),
+                accel(v[15], v[16]),
+            ],
+        };
+        for (axes, range) in [
+            (&mut calibration.gyro, GYRO_RANGE),
+            (&mut calibration.accel, ACC_RANGE),
+        ] {
+            for axis in axes.iter_mut().filter(|axis| axis.denom == 0) {
+                *axis = AxisCalibration {
+                    bias: 0,
+                    numer: range,
+                    denom: i16::MAX as i32,
+                };
+            }
+        }
+        calibration
+    }
+
+    /// Calibrates the raw sensor values of `state`.
+    pub fn apply(&self, state: &InputState) -> MotionSample {
+        let gyro = [0, 1, 2].map(|i| self.gyro[i].apply(state.gyro[i]));
+        let accel = [0, 1, 2].map(|i| self.accel[i].apply(state.accel[i]));
+        MotionSample {
+            gyro: gyro.map(|g| g as f32 / GYRO_RES_PER_DEG_S as f32),
+            accel: accel.map(|a| a as f32 / ACC_RES_PER_G as f32),
+            timestamp_us: state.timestamp_us(),
+        }
+    }
+}
+
+/// Calibrated motion sensor readings of one input report.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct MotionSample {
+    /// Angular velocity around x (pitch), y (yaw) and z (roll) in degrees per
+    /// second.
+    pub gyro: [f32; 3],
+    /// Acceleration along x, y and z in g.
+    pub accel: [f32; 3],
+    /// Sensor clock in microseconds.
+    pub timestamp_us: u32,
+}
@@ -0,0 +1,132 @@
+//! Firmware and pairing information from feature reports.
+//!
+//! Feature report 0x20 holds the firmware build date and the hardware and
+//! firmware versions, report 0x09 the controller's Bluetooth address and the
+//! address of the host it is paired with.
+
+use std::fmt;
+
+use crate::crc;
+use crate::device::{Bus, FEATURE_REPORT_FIRMWARE_INFO, FEATURE_REPORT_PAIRING_INFO};
+use crate::{Error, Result};
+
+pub const PAIRING_INFO_SIZE: usize = 20;
+pub const FIRMWARE_INFO_SIZE: usize = 64;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct FirmwareInfo {
+    /// Build date as written by the firmware, e.g. "Jun 15 2021".
+    pub build_date: String,
+    /// Build time as written by the firmware, e.g. "08:32:03".
+    pub build_time: String,
+    pub hardware_version: u32,
+    pub firmware_version: u32,
+    pub update_version: u16,
+}
+
+impl FirmwareInfo {
+    /// Parses feature report 0x20, report id included. When read over
+    /// Bluetooth the report ends with a checksum, which is verified.
+    pub fn parse(report: &[u8], bus: Bus) -> Result<Self> {
+        let report = check_feature(
+            report,
+            FEATURE_REPORT_FIRMWARE_INFO,
+            FIRMWARE_INFO_SIZE,
+            bus,
+        )?;
+        Ok(FirmwareInfo {
+            build_date: read_str(&report[1..12]),
+            build_time: read_str(&report[12..20]),
+            hardware_version: u32::from_le_bytes([report[24], report[25], report[26], report[27]]),
+            firmware_version: u32::from_le_bytes([report[28], report[29], report[30], report[31]]),
+            update_version: u16::from_le_bytes([report[44], report[45]]),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct PairingInfo {
+    /// Controller MAC address, most significant byte first.
+    pub mac: [u8; 6],
+    /// MAC address of the paired host, most significant byte first.
+    pub host_mac: [u8; 6],
+}
+
+impl PairingInfo {
+    /// Parses feature report 0x09, report id included. When read over
+    /// Bluetooth the report ends with a checksum, which is verified.
+    pub fn parse(report: &[u8], bus: Bus) -> Result<Self> {
+        let report = check_feature(report, FEATURE_REPORT_PAIRING_INFO, PAIRING_INFO_SIZE, bus)?;
+        // Both addresses are stored least significant byte first.
+        let mut info = PairingInfo::default();
+        for i in 0..6 {
+            info.mac[i] = report[6 - i];
+            info.host_mac[i] = report[15 - i];
+        }
+        Ok(info)
+    }
+}
+
+/// Everything the controller tells about itself, see
+/// [`DualSense::info`](crate::DualSense::info).
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct DeviceInfo {
+    pub firmware: FirmwareInfo,
+    pub pairing: PairingInfo,
+}
+
+impl DeviceInfo {
+    /// The controller's MAC address as `aa:bb:cc:dd:ee:ff`. It identifies
+    /// the controller on both buses, and the Linux driver reports it as the
+    /// device's unique id.
+    pub fn serial(&self) -> String {
+        format_mac(&self.pairing.mac)
+    }
+}
+
+impl fmt::Display for DeviceInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let firmware = &self.firmware;
+        writeln!(f, "MAC address: {}", format_mac(&self.pairing.mac))?;
+        writeln!(f, "paired host: {}", format_mac(&self.pairing.host_mac))?;
+        writeln!(
+            f,
+            "firmware built: {} {}",
+            firmware.build_date, firmware.build_time
+        )?;
+        writeln!(f, "hardware version: 0x{:08x}", firmware.hardware_version)?;
+        writeln!(f, "firmware version: 0x{:08x}", firmware.firmware_version)?;
+        write!(f, "update version: 0x{:04x}", firmware.update_version)
+    }
+}
+
+/// Formats a MAC address, most significant byte first, as
+/// `aa:bb:cc:dd:ee:ff`.
+pub fn format_mac(mac: &[u8; 6]) -> String {
+    mac.map(|b| format!("{:02x}", b)).join(":")
+}
+
+/// Checks length, report id and, over Bluetooth, the checksum of a feature
+/// report and cuts it to `size`.
+fn check_feature(report: &[u8], id: u8, size: usize, bus: Bus) -> Result<&[u8]> {
+    if report.len() < size {
+        return Err(Error::ReportTooShort {
+            expected: size,
+            actual: report.len(),
+        });
+    }
+    if report[0] != id {
+        return Err(Error::UnexpectedReportId(report[0]));
+    }
+    let report = &report[..size];
+    if bus == Bus::Bluetooth {
+        crc::verify(crc::SEED_FEATURE, report)
+            .map_err(|(expected, actual)| Error::CrcMismatch { expected, actual })?;
+    }
+    Ok(report)
+}
+
+fn read_str(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).into_owned()
+}
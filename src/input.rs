@@ -0,0 +1,601 @@
+//! Parsing of the controller's input reports.
+//!
+//! Offsets in this module are relative to the first byte after the report
+//! id, which is where the USB and Bluetooth layouts start to agree.
+
+use crate::crc;
+use crate::device::Bus;
+use crate::{Error, Result};
+
+pub const USB_INPUT_REPORT_ID: u8 = 0x01;
+pub const USB_INPUT_REPORT_SIZE: usize = 64;
+pub const BT_INPUT_REPORT_ID: u8 = 0x31;
+pub const BT_INPUT_REPORT_SIZE: usize = 78;
+
+const OFFSET_LEFT_STICK: usize = 0;
+const OFFSET_RIGHT_STICK: usize = 2;
+const OFFSET_L2: usize = 4;
+const OFFSET_R2: usize = 5;
+const OFFSET_SEQUENCE: usize = 6;
+const OFFSET_BUTTONS: usize = 7;
+const OFFSET_GYRO: usize = 15;
+const OFFSET_ACCEL: usize = 21;
+const OFFSET_SENSOR_TIMESTAMP: usize = 27;
+const OFFSET_TOUCH: usize = 32;
+const OFFSET_POWER: usize = 52;
+const OFFSET_PERIPHERALS: usize = 53;
+const COMMON_SIZE: usize = 63;
+
+/// Byte (relative to the first button byte) and mask of every button
+/// except the d-pad, which is a hat switch in the low nibble of byte 0.
+const BUTTON_BITS: [(Button, usize, u8); 15] = [
+    (Button::Square, 0, 0x10),
+    (Button::Cross, 0, 0x20),
+    (Button::Circle, 0, 0x40),
+    (Button::Triangle, 0, 0x80),
+    (Button::L1, 1, 0x01),
+    (Button::R1, 1, 0x02),
+    (Button::L2, 1, 0x04),
+    (Button::R2, 1, 0x08),
+    (Button::Create, 1, 0x10),
+    (Button::Options, 1, 0x20),
+    (Button::L3, 1, 0x40),
+    (Button::R3, 1, 0x80),
+    (Button::Ps, 2, 0x01),
+    (Button::Touchpad, 2, 0x02),
+    (Button::Mute, 2, 0x04),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Button {
+    Square,
+    Cross,
+    Circle,
+    Triangle,
+    L1,
+    R1,
+    L2,
+    R2,
+    Create,
+    Options,
+    L3,
+    R3,
+    Ps,
+    Touchpad,
+    Mute,
+    DPadUp,
+    DPadRight,
+    DPadDown,
+    DPadLeft,
+}
+
+impl Button {
+    pub const ALL: [Button; 19] = [
+        Button::Square,
+        Button::Cross,
+        Button::Circle,
+        Button::Triangle,
+        Button::L1,
+        Button::R1,
+        Button::L2,
+        Button::R2,
+        Button::Create,
+        Button::Options,
+        Button::L3,
+        Button::R3,
+        Button::Ps,
+        Button::Touchpad,
+        Button::Mute,
+        Button::DPadUp,
+        Button::DPadRight,
+        Button::DPadDown,
+        Button::DPadLeft,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Button::Square => "square",
+            Button::Cross => "cross",
+            Button::Circle => "circle",
+            Button::Triangle => "triangle",
+            Button::L1 => "L1",
+            Button::R1 => "R1",
+            Button::L2 => "L2",
+            Button::R2 => "R2",
+            Button::Create => "create",
+            Button::Options => "options",
+            Button::L3 => "L3",
+            Button::R3 => "R3",
+            Button::Ps => "ps",
+            Button::Touchpad => "touchpad",
+            Button::Mute => "mute",
+            Button::DPadUp => "arrow_up",
+            Button::DPadRight => "arrow_right",
+            Button::DPadDown => "arrow_down",
+            Button::DPadLeft => "arrow_left",
+        }
+    }
+
+    fn mask(self) -> u32 {
+        1 << self as u32
+    }
+}
+
+/// The set of buttons held down in one report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Hash)]
+pub struct Buttons(u32);
+
+impl Buttons {
+    pub fn is_pressed(self, button: Button) -> bool {
+        self.0 & button.mask() != 0
+    }
+
+    pub fn set(&mut self, button: Button, pressed: bool) {
+        if pressed {
+            self.0 |= button.mask();
+        } else {
+            self.0 &= !button.mask();
+        }
+    }
+
+    /// Iterates over the buttons that are held down.
+    pub fn iter(self) -> impl Iterator<Item = Button> {
+        Button::ALL.into_iter().filter(move |b| self.is_pressed(*b))
+    }
+}
+
+/// Position of the d-pad hat switch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Hash)]
+pub enum DPad {
+    Up,
+    UpRight,
+    Right,
+    DownRight,
+    Down,
+    DownLeft,
+    Left,
+    UpLeft,
+    #[default]
+    Neutral,
+}
+
+impl DPad {
+    /// Decodes the 4-bit hat value, where 0 is up and values go clockwise.
+    pub fn from_hat(n: u8) -> Self {
+        match n {
+            0 => DPad::Up,
+            1 => DPad::UpRight,
+            2 => DPad::Right,
+            3 => DPad::DownRight,
+            4 => DPad::Down,
+            5 => DPad::DownLeft,
+            6 => DPad::Left,
+            7 => DPad::UpLeft,
+            _ => DPad::Neutral,
+        }
+    }
+
+    /// Combines single directions into a hat position. Opposite directions
+    /// cancel out.
+    pub fn from_directions(up: bool, right: bool, down: bool, left: bool) -> Self {
+        let vertical = up as i8 - down as i8;
+        let horizontal = right as i8 - left as i8;
+        match (vertical, horizontal) {
+            (1, 0) => DPad::Up,
+            (1, 1) => DPad::UpRight,
+            (0, 1) => DPad::Right,
+            (-1, 1) => DPad::DownRight,
+            (-1, 0) => DPad::Down,
+            (-1, -1) => DPad::DownLeft,
+            (0, -1) => DPad::Left,
+            (1, -1) => DPad::UpLeft,
+            _ => DPad::Neutral,
+        }
+    }
+
+    pub fn to_hat(self) -> u8 {
+        match self {
+            DPad::Up => 0,
+            DPad::UpRight => 1,
+            DPad::Right => 2,
+            DPad::DownRight => 3,
+            DPad::Down => 4,
+            DPad::DownLeft => 5,
+            DPad::Left => 6,
+            DPad::UpLeft => 7,
+            DPad::Neutral => 8,
+        }
+    }
+
+    pub fn up(self) -> bool {
+        matches!(self, DPad::UpLeft | DPad::Up | DPad::UpRight)
+    }
+
+    pub fn right(self) -> bool {
+        matches!(self, DPad::UpRight | DPad::Right | DPad::DownRight)
+    }
+
+    pub fn down(self) -> bool {
+        matches!(self, DPad::DownRight | DPad::Down | DPad::DownLeft)
+    }
+
+    pub fn left(self) -> bool {
+        matches!(self, DPad::DownLeft | DPad::Left | DPad::UpLeft)
+    }
+}
+
+/// Raw stick position, 0 is left/up and 255 is right/down.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Stick {
+    pub x: u8,
+    pub y: u8,
+}
+
+impl Default for Stick {
+    fn default() -> Self {
+        Stick { x: 0x80, y: 0x80 }
+    }
+}
+
+impl Stick {
+    /// Position scaled to [-1, 1] on both axes, with 0x80 as exactly 0.
+    /// Negative is left/up.
+    pub fn normalized(self) -> (f32, f32) {
+        (normalize_axis(self.x), normalize_axis(self.y))
+    }
+}
+
+fn normalize_axis(value: u8) -> f32 {
+    let offset = value as f32 - 128.0;
+    if offset < 0.0 {
+        offset / 128.0
+    } else {
+        offset / 127.0
+    }
+}
+
+/// Touchpad resolution; x grows to the right and y downwards.
+pub const TOUCHPAD_WIDTH: u16 = 1920;
+pub const TOUCHPAD_HEIGHT: u16 = 1080;
+
+/// One of the two contacts the touchpad reports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct TouchContact {
+    pub active: bool,
+    /// 7-bit tracking id, incremented by the controller for every new touch.
+    pub id: u8,
+    pub x: u16,
+    pub y: u16,
+}
+
+impl TouchContact {
+    /// Decodes the 4 bytes of a contact: an inactive bit and the tracking id,
+    /// followed by 12-bit x and y packed into 3 bytes.
+    fn parse(data: &[u8]) -> Self {
+        TouchContact {
+            active: data[0] & 0x80 == 0,
+            id: data[0] & 0x7f,
+            x: data[1] as u16 | (data[2] as u16 & 0x0f) << 8,
+            y: (data[2] as u16) >> 4 | (data[3] as u16) << 4,
+        }
+    }
+
+    fn write(&self, data: &mut [u8]) {
+        data[0] = (self.id & 0x7f) | if self.active { 0 } else { 0x80 };
+        data[1] = self.x as u8;
+        data[2] = (self.x >> 8) as u8 & 0x0f | (self.y << 4) as u8;
+        data[3] = (self.y >> 4) as u8;
+    }
+}
+
+/// Charging state from the high nibble of the power status byte.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ChargingState {
+    #[default]
+    Discharging,
+    Charging,
+    Full,
+    /// Not charging because the voltage is out of range.
+    AbnormalVoltage,
+    /// Not charging because the temperature is out of range.
+    AbnormalTemperature,
+    ChargingError,
+    Unknown(u8),
+}
+
+impl ChargingState {
+    pub fn from_nibble(n: u8) -> Self {
+        match n {
+            0x0 => ChargingState::Discharging,
+            0x1 => ChargingState::Charging,
+            0x2 => ChargingState::Full,
+            0xa => ChargingState::AbnormalVoltage,
+            0xb => ChargingState::AbnormalTemperature,
+            0xf => ChargingState::ChargingError,
+            n => ChargingState::Unknown(n),
+        }
+    }
+
+    pub fn to_nibble(self) -> u8 {
+        match self {
+            ChargingState::Discharging => 0x0,
+            ChargingState::Charging => 0x1,
+            ChargingState::Full => 0x2,
+            ChargingState::AbnormalVoltage => 0xa,
+            ChargingState::AbnormalTemperature => 0xb,
+            ChargingState::ChargingError => 0xf,
+            ChargingState::Unknown(n) => n & 0x0f,
+        }
+    }
+
+    pub fn is_error(self) -> bool {
+        !matches!(
+            self,
+            ChargingState::Discharging | ChargingState::Charging | ChargingState::Full
+        )
+    }
+}
+
+/// Battery and charger state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct PowerStatus {
+    /// Battery level in steps of 10%, 0 to 10.
+    pub level: u8,
+    pub charging: ChargingState,
+}
+
+impl PowerStatus {
+    /// Battery charge in percent, computed like the Linux driver does: the
+    /// middle of the level's 10% step, 100 when full and 0 when the state is
+    /// an error.
+    pub fn percentage(&self) -> u8 {
+        match self.charging {
+            ChargingState::Discharging | ChargingState::Charging => {
+                (self.level.min(10) * 10 + 5).min(100)
+            }
+            ChargingState::Full => 100,
+            _ => 0,
+        }
+    }
+}
+
+/// What is plugged into the headset jack.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct PeripheralStatus {
+    pub headphones: bool,
+    pub microphone: bool,
+    pub mic_muted: bool,
+}
+
+/// Decoded contents of one input report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct InputState {
+    pub left_stick: Stick,
+    pub right_stick: Stick,
+    /// Analog trigger values, 0 is released and 255 fully pulled.
+    pub l2: u8,
+    pub r2: u8,
+    pub buttons: Buttons,
+    pub dpad: DPad,
+    /// Raw angular velocity around the x (pitch), y (yaw) and z (roll) axes,
+    /// see [`ImuCalibration`](crate::ImuCalibration) for physical units.
+    pub gyro: [i16; 3],
+    /// Raw acceleration along the x, y and z axes.
+    pub accel: [i16; 3],
+    pub touch: [TouchContact; 2],
+    pub power: PowerStatus,
+    pub peripherals: PeripheralStatus,
+    /// Counter incremented by the controller for every report.
+    pub sequence: u8,
+    /// Sensor clock in units of 1/3 microsecond.
+    pub sensor_timestamp: u32,
+}
+
+impl InputState {
+    /// Parses a USB input report 0x01 or a Bluetooth extended input report
+    /// 0x31, report id included.
+    ///
+    /// Bluetooth reports whose checksum does not match are rejected with
+    /// [`Error::CrcMismatch`].
+    pub fn parse(report: &[u8]) -> Result<Self> {
+        Self::parse_with_bus(report).map(|(state, _)| state)
+    }
+
+    /// Like [`InputState::parse`], also returning which layout was detected.
+    pub fn parse_with_bus(report: &[u8]) -> Result<(Self, Bus)> {
+        match report.first() {
+            Some(&USB_INPUT_REPORT_ID) => {
+                check_len(report, USB_INPUT_REPORT_SIZE)?;
+                Ok((Self::parse_common(&report[1..]), Bus::Usb))
+            }
+            Some(&BT_INPUT_REPORT_ID) => {
+                check_len(report, BT_INPUT_REPORT_SIZE)?;
+                let report = &report[..BT_INPUT_REPORT_SIZE];
+                crc::verify(crc::SEED_INPUT, report)
+                    .map_err(|(expected, actual)| Error::CrcMismatch { expected, actual })?;
+                // Byte 1 holds the Bluetooth sequence tag, the shared layout
+                // starts one byte later than over USB.
+                Ok((Self::parse_common(&report[2..]), Bus::Bluetooth))
+            }
+            Some(&id) => Err(Error::UnexpectedReportId(id)),
+            None => Err(Error::ReportTooShort {
+                expected: USB_INPUT_REPORT_SIZE,
+                actual: 0,
+            }),
+        }
+    }
+
+    /// Encodes as USB input report 0x01, the inverse of [`InputState::parse`].
+    ///
+    /// The d-pad is taken from the `DPad*` buttons; the `dpad` field is
+    /// ignored.
+    pub fn to_usb_bytes(&self) -> [u8; USB_INPUT_REPORT_SIZE] {
+        let mut report = [0; USB_INPUT_REPORT_SIZE];
+        report[0] = USB_INPUT_REPORT_ID;
+        self.write_common(&mut report[1..]);
+        report
+    }
+
+    /// Encodes as Bluetooth input report 0x31 with a valid checksum.
+    pub fn to_bluetooth_bytes(&self) -> [u8; BT_INPUT_REPORT_SIZE] {
+        let mut report = [0; BT_INPUT_REPORT_SIZE];
+        report[0] = BT_INPUT_REPORT_ID;
+        self.write_common(&mut report[2..]);
+        let crc = crc::crc32(crc::SEED_INPUT, &report[..BT_INPUT_REPORT_SIZE - 4]);
+        report[BT_INPUT_REPORT_SIZE - 4..].copy_from_slice(&crc.to_le_bytes());
+        report
+    }
+
+    /// Encodes for the given bus.
+    pub fn to_bytes(&self, bus: Bus) -> Vec<u8> {
+        match bus {
+            Bus::Usb => self.to_usb_bytes().to_vec(),
+            Bus::Bluetooth => self.to_bluetooth_bytes().to_vec(),
+        }
+    }
+
+    /// Sensor timestamp converted to microseconds.
+    pub fn timestamp_us(&self) -> u32 {
+        self.sensor_timestamp / 3
+    }
+
+    pub fn is_pressed(&self, button: Button) -> bool {
+        self.buttons.is_pressed(button)
+    }
+
+    fn parse_common(data: &[u8]) -> Self {
+        debug_assert!(data.len() >= COMMON_SIZE);
+
+        let raw = &data[OFFSET_BUTTONS..OFFSET_BUTTONS + 3];
+        let dpad = DPad::from_hat(raw[0] & 0x0f);
+
+        let mut buttons = Buttons::default();
+        for (button, byte, mask) in BUTTON_BITS {
+            buttons.set(button, raw[byte] & mask != 0);
+        }
+        buttons.set(Button::DPadUp, dpad.up());
+        buttons.set(Button::DPadRight, dpad.right());
+        buttons.set(Button::DPadDown, dpad.down());
+        buttons.set(Button::DPadLeft, dpad.left());
+
+        InputState {
+            left_stick: Stick {
+                x: data[OFFSET_LEFT_STICK],
+                y: data[OFFSET_LEFT_STICK + 1],
+            },
+            right_stick: Stick {
+                x: data[OFFSET_RIGHT_STICK],
+                y: data[OFFSET_RIGHT_STICK + 1],
+            },
+            l2: data[OFFSET_L2],
+            r2: data[OFFSET_R2],
+            buttons,
+            dpad,
+            gyro: read_i16_triple(data, OFFSET_GYRO),
+            accel: read_i16_triple(data, OFFSET_ACCEL),
+            touch: [0, 1].map(|i| TouchContact::parse(&data[OFFSET_TOUCH + i * 4..])),
+            power: PowerStatus {
+                level: data[OFFSET_POWER] & 0x0f,
+                charging: ChargingState::from_nibble(data[OFFSET_POWER] >> 4),
+            },
+            peripherals: PeripheralStatus {
+                headphones: data[OFFSET_PERIPHERALS] & 0x01 != 0,
+                microphone: data[OFFSET_PERIPHERALS] & 0x02 != 0,
+                mic_muted: data[OFFSET_PERIPHERALS] & 0x04 != 0,
+            },
+            sequence: data[OFFSET_SEQUENCE],
+            sensor_timestamp: read_u32_le(data, OFFSET_SENSOR_TIMESTAMP),
+        }
+    }
+
+    fn write_common(&self, data: &mut [u8]) {
+        debug_assert!(data.len() >= COMMON_SIZE);
+
+        data[OFFSET_LEFT_STICK] = self.left_stick.x;
+        data[OFFSET_LEFT_STICK + 1] = self.left_stick.y;
+        data[OFFSET_RIGHT_STICK] = self.right_stick.x;
+        data[OFFSET_RIGHT_STICK + 1] = self.right_stick.y;
+        data[OFFSET_L2] = self.l2;
+        data[OFFSET_R2] = self.r2;
+        data[OFFSET_SEQUENCE] = self.sequence;
+
+        let dpad = DPad::from_directions(
+            self.is_pressed(Button::DPadUp),
+            self.is_pressed(Button::DPadRight),
+            self.is_pressed(Button::DPadDown),
+            self.is_pressed(Button::DPadLeft),
+        );
+        let raw = &mut data[OFFSET_BUTTONS..OFFSET_BUTTONS + 3];
+        raw[0] = dpad.to_hat();
+        for (button, byte, mask) in BUTTON_BITS {
+            if self.is_pressed(button) {
+                raw[byte] |= mask;
+            }
+        }
+
+        for i in 0..3 {
+            data[OFFSET_GYRO + i * 2..OFFSET_GYRO + i * 2 + 2]
+                .copy_from_slice(&self.gyro[i].to_le_bytes());
+            data[OFFSET_ACCEL + i * 2..OFFSET_ACCEL + i * 2 + 2]
+                .copy_from_slice(&self.accel[i].to_le_bytes());
+        }
+        data[OFFSET_SENSOR_TIMESTAMP..OFFSET_SENSOR_TIMESTAMP + 4]
+            .copy_from_slice(&self.sensor_timestamp.to_le_bytes());
+        for (i, contact) in self.touch.iter().enumerate() {
+            contact.write(&mut data[OFFSET_TOUCH + i * 4..]);
+        }
+        data[OFFSET_POWER] = self.power.charging.to_nibble() << 4 | self.power.level & 0x0f;
+        data[OFFSET_PERIPHERALS] = self.peripherals.headphones as u8
+            | (self.peripherals.microphone as u8) << 1
+            | (self.peripherals.mic_muted as u8) << 2;
+    }
+}
+
+/// Parses a stream of input reports and keeps count of the ones dropped
+/// because of a bad checksum.
+#[derive(Debug, Default)]
+pub struct InputParser {
+    dropped: u64,
+}
+
+impl InputParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn parse(&mut self, report: &[u8]) -> Result<InputState> {
+        let result = InputState::parse(report);
+        if let Err(Error::CrcMismatch { .. }) = result {
+            self.dropped += 1;
+        }
+        result
+    }
+
+    /// Number of reports rejected because their checksum did not match.
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+}
+
+fn check_len(report: &[u8], expected: usize) -> Result<()> {
+    if report.len() < expected {
+        return Err(Error::ReportTooShort {
+            expected,
+            actual: report.len(),
+        });
+    }
+    Ok(())
+}
+
+fn read_u32_le(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        data[offset],
+        data[offset + 1],
+        data[offset + 2],
+        data[offset + 3],
+    ])
+}
+
+fn read_i16_triple(data: &[u8], offset: usize) -> [i16; 3] {
+    [0, 1, 2].map(|i| i16::from_le_bytes([data[offset + i * 2], data[offset + i * 2 + 1]]))
+}
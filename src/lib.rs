@@ -0,0 +1,64 @@
+//! Talk to a Sony DualSense controller over USB or Bluetooth.
+//!
+//! [`DualSense`] wraps a [`Transport`] and gives access to the input and
+//! output reports. [`InputState::parse`] decodes an input report without
+//! needing a controller attached, and [`OutputReport`] builds the report that
+//! sets rumble, LEDs and triggers. [`SimulatedDualSense`] stands in for a
+//! real controller when none is attached.
+
+pub mod capture;
+pub mod clock;
+mod crc;
+pub mod device;
+pub mod discovery;
+pub mod error;
+pub mod events;
+pub mod gesture;
+pub mod hotplug;
+pub mod imu;
+pub mod info;
+pub mod input;
+pub mod lightbar;
+pub mod manager;
+pub mod mapping;
+pub mod orientation;
+pub mod output;
+pub mod rumble;
+pub mod simulated;
+pub mod stick;
+pub mod touch;
+pub mod transport;
+
+pub use capture::{CaptureHeader, CaptureReader, CaptureWriter, Recorder, Replay, ReplaySpeed};
+pub use clock::{Clock, FakeClock, SystemClock};
+pub use device::{
+    Bus, DualSense, DUALSENSE_EDGE_PRODUCT_ID, DUALSENSE_PRODUCT_ID, DUALSHOCK4_PRODUCT_ID,
+    DUALSHOCK4_V2_PRODUCT_ID, SONY_VENDOR_ID,
+};
+pub use discovery::{discover, ControllerModel, DeviceId, DiscoveredDevice, TransportKind};
+pub use error::{Error, Result};
+pub use events::{Axis, Event, EventConfig, EventGenerator, Events};
+pub use gesture::{Gesture, GestureConfig, GestureRecognizer, SwipeDirection};
+pub use hotplug::{HotplugEvent, HotplugMonitor, ReattachingDualSense};
+pub use imu::{AxisCalibration, ImuCalibration, MotionSample};
+pub use info::{DeviceInfo, FirmwareInfo, PairingInfo};
+pub use input::{
+    Button, Buttons, ChargingState, DPad, InputParser, InputState, PeripheralStatus, PowerStatus,
+    Stick, TouchContact,
+};
+pub use lightbar::{Animation, Color, Easing, Keyframe, LightbarAnimator};
+pub use manager::{ControllerManager, SlotEvent, SlotSnapshot, MAX_PLAYERS};
+pub use mapping::{ButtonMapping, ControllerMapping, MappedState, MappedValue};
+pub use orientation::{EulerAngles, FusionAlgorithm, OrientationFilter, Quaternion};
+pub use output::{
+    LedBrightness, MuteLed, OutputReport, OutputState, PlayerLeds, Trigger, TriggerEffect,
+};
+pub use rumble::{RumblePattern, RumbleScheduler, RumbleSegment};
+pub use simulated::SimulatedDualSense;
+pub use stick::{Deadzone, ResponseCurve, StickConfig, StickPosition, StickProcessor};
+pub use touch::{Finger, FingerTracker, TouchEvent};
+#[cfg(target_os = "linux")]
+pub use transport::hidraw::HidrawTransport;
+pub use transport::mock::MockTransport;
+pub use transport::usb::UsbTransport;
+pub use transport::Transport;
@@ -0,0 +1,298 @@
+//! Lightbar animations.
+//!
+//! An [`Animation`] maps time to a colour. [`LightbarAnimator`] samples it on
+//! a fixed tick and writes the colour into an [`OutputReport`], reading the
+//! time from a [`Clock`].
+
+use std::f32::consts::PI;
+use std::time::Duration;
+
+use crate::clock::{Clock, SystemClock};
+use crate::device::DualSense;
+use crate::output::OutputReport;
+use crate::transport::Transport;
+use crate::Result;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Color {
+    pub const BLACK: Color = Color::new(0, 0, 0);
+    pub const WHITE: Color = Color::new(0xff, 0xff, 0xff);
+
+    pub const fn new(r: u8, g: u8, b: u8) -> Self {
+        Color { r, g, b }
+    }
+
+    /// Fully saturated colour of the given hue in degrees.
+    pub fn from_hue(hue: f32) -> Self {
+        let h = hue.rem_euclid(360.0) / 60.0;
+        let x = 1.0 - (h % 2.0 - 1.0).abs();
+        let (r, g, b) = match h as u32 {
+            0 => (1.0, x, 0.0),
+            1 => (x, 1.0, 0.0),
+            2 => (0.0, 1.0, x),
+            3 => (0.0, x, 1.0),
+            4 => (x, 0.0, 1.0),
+            _ => (1.0, 0.0, x),
+        };
+        Color::new(channel(r), channel(g), channel(b))
+    }
+
+    /// Colour `t` of the way from `self` to `other`, `t` in [0, 1].
+    pub fn lerp(self, other: Color, t: f32) -> Self {
+        let mix = |a: u8, b: u8| channel((a as f32 + (b as f32 - a as f32) * t) / 255.0);
+        Color::new(
+            mix(self.r, other.r),
+            mix(self.g, other.g),
+            mix(self.b, other.b),
+        )
+    }
+
+    /// Colour scaled by `brightness` in [0, 1].
+    pub fn scale(self, brightness: f32) -> Self {
+        Color::BLACK.lerp(self, brightness)
+    }
+}
+
+fn channel(value: f32) -> u8 {
+    (value.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+/// How a keyframe is approached from the previous one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Easing {
+    #[default]
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+    /// Jumps to the keyframe's colour when it is reached.
+    Step,
+}
+
+impl Easing {
+    /// Maps progress `t` in [0, 1] to eased progress in [0, 1].
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t,
+            Easing::EaseOut => t * (2.0 - t),
+            Easing::EaseInOut => t * t * (3.0 - 2.0 * t),
+            Easing::Step => {
+                if t < 1.0 {
+                    0.0
+                } else {
+                    1.0
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Keyframe {
+    /// Time from the start of the animation.
+    pub at: Duration,
+    pub color: Color,
+    /// Easing from the previous keyframe to this one.
+    pub easing: Easing,
+}
+
+impl Keyframe {
+    pub fn new(at: Duration, color: Color, easing: Easing) -> Self {
+        Keyframe { at, color, easing }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Animation {
+    Solid(Color),
+    /// Fades smoothly between off and `color` once per `period`.
+    Breathe {
+        color: Color,
+        period: Duration,
+    },
+    /// Lights up at the start of every `period` and fades out.
+    Pulse {
+        color: Color,
+        period: Duration,
+    },
+    /// Runs through the hues once per `period`.
+    Rainbow {
+        period: Duration,
+    },
+    /// On for `duty` of every `period`, off for the rest.
+    Strobe {
+        color: Color,
+        period: Duration,
+        duty: f32,
+    },
+    /// Interpolates between keyframes ordered by time. Holds the first colour
+    /// before the first keyframe and the last one after the last, unless
+    /// `looped` restarts the timeline at the last keyframe.
+    Keyframes {
+        frames: Vec<Keyframe>,
+        looped: bool,
+    },
+}
+
+impl Animation {
+    /// Colour at `t` after the start of the animation.
+    pub fn color_at(&self, t: Duration) -> Color {
+        match self {
+            Animation::Solid(color) => *color,
+            Animation::Breathe { color, period } => {
+                color.scale((1.0 - (2.0 * PI * phase(t, *period)).cos()) / 2.0)
+            }
+            Animation::Pulse { color, period } => {
+                color.scale(1.0 - Easing::EaseOut.apply(phase(t, *period)))
+            }
+            Animation::Rainbow { period } => Color::from_hue(phase(t, *period) * 360.0),
+            Animation::Strobe {
+                color,
+                period,
+                duty,
+            } => {
+                if phase(t, *period) < *duty {
+                    *color
+                } else {
+                    Color::BLACK
+                }
+            }
+            Animation::Keyframes { frames, looped } => keyframe_color(frames, *looped, t),
+        }
+    }
+}
+
+/// Position of `t` within the current `period`, in [0, 1).
+fn phase(t: Duration, period: Duration) -> f32 {
+    if period.is_zero() {
+        return 0.0;
+    }
+    (t.as_secs_f64() / period.as_secs_f64()).fract() as f32
+}
+
+fn keyframe_color(frames: &[Keyframe], looped: bool, t: Duration) -> Color {
+    let (first, last) = match (frames.first(), frames.last()) {
+        (Some(first), Some(last)) => (first, last),
+        _ => return Color::BLACK,
+    };
+    let t = if looped && !last.at.is_zero() {
+        Duration::from_secs_f64(t.as_secs_f64() % last.at.as_secs_f64())
+    } else {
+        t
+    };
+    if t <= first.at {
+        return first.color;
+    }
+
+    for pair in frames.windows(2) {
+        let (from, to) = (&pair[0], &pair[1]);
+        if t < to.at {
+            let span = to.at.saturating_sub(from.at).as_secs_f32();
+            let progress = if span > 0.0 {
+                (t - from.at).as_secs_f32() / span
+            } else {
+                1.0
+            };
+            return from.color.lerp(to.color, to.easing.apply(progress));
+        }
+    }
+    last.color
+}
+
+/// Plays an [`Animation`] on the lightbar at a fixed tick.
+pub struct LightbarAnimator<C: Clock = SystemClock> {
+    clock: C,
+    animation: Animation,
+    started: Duration,
+    tick: Duration,
+    next_tick: Duration,
+}
+
+impl LightbarAnimator<SystemClock> {
+    pub fn new(animation: Animation) -> Self {
+        Self::with_clock(animation, SystemClock::new())
+    }
+}
+
+impl<C: Clock> LightbarAnimator<C> {
+    /// Animator that starts `animation` now on `clock`, ticking every 16 ms.
+    pub fn with_clock(animation: Animation, clock: C) -> Self {
+        let now = clock.now();
+        LightbarAnimator {
+            clock,
+            animation,
+            started: now,
+            tick: Duration::from_millis(16),
+            next_tick: now,
+        }
+    }
+
+    pub fn set_tick(&mut self, tick: Duration) {
+        self.tick = tick;
+    }
+
+    pub fn animation(&self) -> &Animation {
+        &self.animation
+    }
+
+    /// Switches to `animation`, starting it from the beginning now.
+    pub fn set_animation(&mut self, animation: Animation) {
+        self.animation = animation;
+        self.started = self.clock.now();
+        self.next_tick = self.started;
+    }
+
+    /// Colour of the animation at the current time.
+    pub fn current_color(&self) -> Color {
+        self.animation
+            .color_at(self.clock.now().saturating_sub(self.started))
+    }
+
+    /// Writes the current colour into `report` if a tick is due and returns
+    /// it. Ticks missed while not called are skipped rather than replayed.
+    pub fn update(&mut self, report: &mut OutputReport) -> Option<Color> {
+        let now = self.clock.now();
+        if now < self.next_tick {
+            return None;
+        }
+        while self.next_tick <= now && !self.tick.is_zero() {
+            self.next_tick += self.tick;
+        }
+
+        let color = self.current_color();
+        report.lightbar(color.r, color.g, color.b);
+        Some(color)
+    }
+
+    /// Time left until the next tick is due.
+    pub fn until_next_tick(&self) -> Duration {
+        self.next_tick.saturating_sub(self.clock.now())
+    }
+
+    /// Sends `report` with the animated lightbar on every tick for
+    /// `duration`.
+    pub fn run<T: Transport>(
+        &mut self,
+        dualsense: &mut DualSense<T>,
+        report: &mut OutputReport,
+        duration: Duration,
+    ) -> Result<()> {
+        let end = self.clock.now() + duration;
+        while self.clock.now() < end {
+            if self.update(report).is_some() {
+                dualsense.send(report)?;
+            }
+            self.clock
+                .sleep(self.until_next_tick().max(Duration::from_micros(100)));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,359 @@
+//! Several controllers at once, each in a player slot.
+//!
+//! [`ControllerManager`] gives every controller the first free of four
+//! player slots and lights its player LEDs to match. Slots belong to a
+//! controller's serial: one that disconnects keeps its slot for a grace
+//! period and gets it back if it returns in time. With a [`HotplugMonitor`]
+//! the manager also connects and disconnects controllers by itself.
+
+use std::time::Duration;
+
+use crate::clock::{Clock, SystemClock};
+use crate::device::DualSense;
+use crate::discovery::{ControllerModel, DeviceId, DiscoveredDevice, TransportKind};
+use crate::hotplug::{HotplugEvent, HotplugMonitor};
+use crate::input::InputState;
+use crate::output::OutputReport;
+use crate::transport::Transport;
+use crate::{Error, Result};
+
+/// One slot for each player LED pattern.
+pub const MAX_PLAYERS: usize = 4;
+
+/// Shortest wait for an input report. libusb takes a zero timeout as no
+/// timeout at all.
+const MIN_READ_TIMEOUT: Duration = Duration::from_millis(1);
+
+/// How often a device that could not be opened or identified is retried.
+const RETRY_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SlotEvent {
+    /// A controller took a free slot.
+    Assigned { player: u8, serial: String },
+    /// A controller came back to the slot reserved for it.
+    Reconnected { player: u8 },
+    /// A controller went away; its slot stays reserved for the grace period.
+    Disconnected { player: u8 },
+    /// The grace period passed and the slot is free again.
+    Released { player: u8, serial: String },
+}
+
+/// State of one slot at the time of the call.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlotSnapshot {
+    /// Player number, 1 to [`MAX_PLAYERS`].
+    pub player: u8,
+    pub serial: String,
+    pub connected: bool,
+    /// Last state read from the controller, kept while it is disconnected.
+    pub state: Option<InputState>,
+    /// How long the controller has been gone, while its slot is reserved.
+    pub disconnected_for: Option<Duration>,
+}
+
+struct Slot<T: Transport> {
+    serial: String,
+    dualsense: Option<DualSense<T>>,
+    device: Option<DeviceId>,
+    state: Option<InputState>,
+    disconnected_at: Option<Duration>,
+}
+
+type Opener<T> = Box<dyn FnMut(&DiscoveredDevice) -> Result<T>>;
+
+/// Owns up to [`MAX_PLAYERS`] controllers and keeps them in stable player
+/// slots.
+pub struct ControllerManager<T: Transport = Box<dyn Transport>, C: Clock = SystemClock> {
+    clock: C,
+    slots: [Option<Slot<T>>; MAX_PLAYERS],
+    grace_period: Duration,
+    hotplug: Option<(HotplugMonitor<C>, Opener<T>)>,
+    /// Devices that failed to open or to tell their serial, with the time
+    /// of the next attempt.
+    pending: Vec<(DiscoveredDevice, Duration)>,
+    transport: TransportKind,
+}
+
+impl<T: Transport> ControllerManager<T, SystemClock> {
+    pub fn new() -> Self {
+        Self::with_clock(SystemClock::new())
+    }
+}
+
+impl<T: Transport> Default for ControllerManager<T, SystemClock> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C: Clock> ControllerManager<Box<dyn Transport>, C> {
+    /// Connects controllers as `monitor` finds them and disconnects them as
+    /// they go away.
+    pub fn watch(&mut self, monitor: HotplugMonitor<C>) {
+        self.watch_with(monitor, |device| device.open());
+    }
+}
+
+impl<T: Transport, C: Clock> ControllerManager<T, C> {
+    /// Manager without controllers on `clock`, with a 30 second grace
+    /// period.
+    pub fn with_clock(clock: C) -> Self {
+        ControllerManager {
+            clock,
+            slots: Default::default(),
+            grace_period: Duration::from_secs(30),
+            hotplug: None,
+            pending: Vec::new(),
+            transport: if cfg!(target_os = "linux") {
+                TransportKind::Hidraw
+            } else {
+                TransportKind::Usb
+            },
+        }
+    }
+
+    /// How long a slot stays reserved after its controller disconnected.
+    pub fn set_grace_period(&mut self, grace_period: Duration) {
+        self.grace_period = grace_period;
+    }
+
+    /// Which transport controllers found by the hotplug monitor are opened
+    /// through. A controller plugged in over USB is seen through both libusb
+    /// and hidraw, and must only be opened once. Defaults to hidraw on Linux
+    /// and libusb elsewhere.
+    pub fn set_transport(&mut self, transport: TransportKind) {
+        self.transport = transport;
+    }
+
+    /// Like [`ControllerManager::watch`], opening devices with `open`.
+    pub fn watch_with<F>(&mut self, monitor: HotplugMonitor<C>, open: F)
+    where
+        F: FnMut(&DiscoveredDevice) -> Result<T> + 'static,
+    {
+        self.hotplug = Some((monitor, Box::new(open)));
+    }
+
+    /// Puts a controller into the slot reserved for `serial`, or the first
+    /// free one, lights its player LEDs and returns the player number. Returns
+    /// `None`, dropping the controller, when all slots are taken.
+    pub fn connect(&mut self, serial: &str, dualsense: DualSense<T>) -> Option<u8> {
+        self.assign(serial, dualsense, None)?;
+        self.player(serial)
+    }
+
+    /// Drops the controller of `player`, keeping the slot reserved for the
+    /// grace period.
+    pub fn disconnect(&mut self, player: u8) -> Option<SlotEvent> {
+        let now = self.clock.now();
+        let slot = self.slot_mut(player)?;
+        slot.dualsense.take()?;
+        slot.device = None;
+        slot.disconnected_at = Some(now);
+        Some(SlotEvent::Disconnected { player })
+    }
+
+    /// Handles hotplug events, reads one input report from every connected
+    /// controller and frees the slots whose grace period has passed.
+    ///
+    /// Only the first controller is waited on, for up to `timeout`; the
+    /// others get a millisecond, so an idle one does not hold up the rest.
+    ///
+    /// A controller whose transport fails is disconnected; timeouts and bad
+    /// reports are not errors here.
+    pub fn poll(&mut self, timeout: Duration) -> Result<Vec<SlotEvent>> {
+        let mut events = self.poll_hotplug()?;
+
+        let mut timeout = timeout.max(MIN_READ_TIMEOUT);
+        for player in 1..=MAX_PLAYERS as u8 {
+            let Some(slot) = self.slot_mut(player) else {
+                continue;
+            };
+            let Some(dualsense) = &mut slot.dualsense else {
+                continue;
+            };
+            let read = dualsense.read_state(timeout);
+            timeout = MIN_READ_TIMEOUT;
+            match read {
+                Ok(state) => slot.state = Some(state),
+                Err(Error::Usb(_) | Error::Io(_)) => events.extend(self.disconnect(player)),
+                Err(_) => {}
+            }
+        }
+
+        let now = self.clock.now();
+        for (i, entry) in self.slots.iter_mut().enumerate() {
+            let expired = entry.as_ref().is_some_and(|slot| {
+                slot.disconnected_at
+                    .is_some_and(|at| now.saturating_sub(at) >= self.grace_period)
+            });
+            if expired {
+                let slot = entry.take().expect("slot is occupied");
+                events.push(SlotEvent::Released {
+                    player: i as u8 + 1,
+                    serial: slot.serial,
+                });
+            }
+        }
+        Ok(events)
+    }
+
+    /// Snapshots of the occupied and reserved slots, by player number.
+    pub fn slots(&self) -> Vec<SlotSnapshot> {
+        (1..=MAX_PLAYERS as u8)
+            .filter_map(|player| self.slot(player))
+            .collect()
+    }
+
+    pub fn slot(&self, player: u8) -> Option<SlotSnapshot> {
+        let slot = self.slots.get(player.checked_sub(1)? as usize)?.as_ref()?;
+        let now = self.clock.now();
+        Some(SlotSnapshot {
+            player,
+            serial: slot.serial.clone(),
+            connected: slot.dualsense.is_some(),
+            state: slot.state,
+            disconnected_for: slot.disconnected_at.map(|at| now.saturating_sub(at)),
+        })
+    }
+
+    /// Player number of the slot that belongs to `serial`.
+    pub fn player(&self, serial: &str) -> Option<u8> {
+        self.slots
+            .iter()
+            .position(|slot| {
+                slot.as_ref()
+                    .is_some_and(|slot| slot.serial.eq_ignore_ascii_case(serial))
+            })
+            .map(|i| i as u8 + 1)
+    }
+
+    pub fn dualsense(&mut self, player: u8) -> Option<&mut DualSense<T>> {
+        self.slot_mut(player)?.dualsense.as_mut()
+    }
+
+    /// Sends `report` to the controller of `player`, with the player LEDs
+    /// set to its slot.
+    pub fn send(&mut self, player: u8, report: &OutputReport) -> Result<()> {
+        let dualsense = self
+            .dualsense(player)
+            .ok_or(Error::NoController { player })?;
+        let mut report = *report;
+        dualsense.send(report.player(player))
+    }
+
+    fn slot_mut(&mut self, player: u8) -> Option<&mut Slot<T>> {
+        self.slots
+            .get_mut(player.checked_sub(1)? as usize)?
+            .as_mut()
+    }
+
+    fn assign(
+        &mut self,
+        serial: &str,
+        mut dualsense: DualSense<T>,
+        device: Option<DeviceId>,
+    ) -> Option<SlotEvent> {
+        let reserved = self.player(serial).map(|player| player as usize - 1);
+        let index = reserved.or_else(|| self.slots.iter().position(Option::is_none))?;
+        let player = index as u8 + 1;
+        if let Some(slot) = &self.slots[index] {
+            if slot.dualsense.is_some() {
+                return None;
+            }
+        }
+
+        dualsense.send(OutputReport::new().player(player)).ok();
+        let state = self.slots[index].as_ref().and_then(|slot| slot.state);
+        self.slots[index] = Some(Slot {
+            serial: serial.to_ascii_lowercase(),
+            dualsense: Some(dualsense),
+            device,
+            state,
+            disconnected_at: None,
+        });
+        Some(match reserved {
+            Some(_) => SlotEvent::Reconnected { player },
+            None => SlotEvent::Assigned {
+                player,
+                serial: serial.to_ascii_lowercase(),
+            },
+        })
+    }
+
+    fn poll_hotplug(&mut self) -> Result<Vec<SlotEvent>> {
+        let Some((monitor, _)) = &mut self.hotplug else {
+            return Ok(Vec::new());
+        };
+        let mut events = Vec::new();
+        for event in monitor.poll(Duration::ZERO)? {
+            match event {
+                HotplugEvent::Disconnected(id) => {
+                    let player = self.slots.iter().position(|slot| {
+                        slot.as_ref()
+                            .is_some_and(|slot| slot.device.as_ref() == Some(&id))
+                    });
+                    if let Some(i) = player {
+                        events.extend(self.disconnect(i as u8 + 1));
+                    }
+                    self.pending.retain(|(device, _)| device.id() != id);
+                }
+                HotplugEvent::Connected { device, .. } => events.extend(self.try_open(device)),
+            }
+        }
+
+        let now = self.clock.now();
+        let (due, waiting) = std::mem::take(&mut self.pending)
+            .into_iter()
+            .partition(|(_, retry_at)| *retry_at <= now);
+        self.pending = waiting;
+        for (device, _) in due {
+            events.extend(self.try_open(device));
+        }
+        Ok(events)
+    }
+
+    /// Opens `device`, or queues it for another attempt if that fails.
+    fn try_open(&mut self, device: DiscoveredDevice) -> Option<SlotEvent> {
+        match self.open(&device) {
+            Ok(event) => event,
+            Err(_) => {
+                let retry_at = self.clock.now() + RETRY_INTERVAL;
+                self.pending.push((device, retry_at));
+                None
+            }
+        }
+    }
+
+    /// Opens a newly found DualSense and gives it a slot, unless it is
+    /// already connected through another device.
+    fn open(&mut self, device: &DiscoveredDevice) -> Result<Option<SlotEvent>> {
+        if device.transport != self.transport || device.model == ControllerModel::DualShock4 {
+            return Ok(None);
+        }
+        let connected = |manager: &Self, serial: &str| {
+            manager
+                .player(serial)
+                .and_then(|player| manager.slots[player as usize - 1].as_ref())
+                .is_some_and(|slot| slot.dualsense.is_some())
+        };
+        if let Some(serial) = &device.serial {
+            if connected(self, serial) {
+                return Ok(None);
+            }
+        }
+
+        let Some((_, open)) = self.hotplug.as_mut() else {
+            return Ok(None);
+        };
+        let mut dualsense = DualSense::new(open(device)?);
+        let serial = match &device.serial {
+            Some(serial) => serial.clone(),
+            None => dualsense.info()?.serial(),
+        };
+        if connected(self, &serial) {
+            return Ok(None);
+        }
+        Ok(self.assign(&serial, dualsense, Some(device.id())))
+    }
+}
@@ -0,0 +1,197 @@
+//! Button layouts loaded from TOML or JSON instead of being compiled in.
+//!
+//! A [`ControllerMapping`] is a list of named bit fields in the USB input
+//! report. Each field is extracted and then turned into either a pressed
+//! state or a plain value, see `mappings/dualsense.toml` for the format.
+
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::input::{BT_INPUT_REPORT_ID, USB_INPUT_REPORT_SIZE};
+use crate::{Error, Result};
+
+const DUALSENSE_MAPPING: &str = include_str!("../mappings/dualsense.toml");
+
+/// One named field of the input report.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ButtonMapping {
+    pub name: String,
+    /// First bit of the field, counted from the start of the USB report.
+    pub bit_offset: u32,
+    /// Width of the field, 1 to 32 bits.
+    pub bits: u32,
+    /// Pressed when this bit of the field is set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bit: Option<u8>,
+    /// Pressed when the field holds one of these values. Takes precedence
+    /// over `bit`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub values: Option<Vec<u32>>,
+}
+
+impl ButtonMapping {
+    /// Decodes this field from a USB 0x01 or Bluetooth 0x31 input report.
+    /// Returns `None` if the field lies outside the report.
+    pub fn decode(&self, report: &[u8]) -> Option<MappedValue> {
+        // Bluetooth reports carry an extra header byte before the layout
+        // shared with USB.
+        let shift = match report.first() {
+            Some(&BT_INPUT_REPORT_ID) => 8,
+            _ => 0,
+        };
+        let n = extract_bits(
+            report,
+            self.bit_offset.checked_add(shift)? as usize,
+            self.bits,
+        )?;
+
+        if let Some(values) = &self.values {
+            return Some(MappedValue::Pressed(values.contains(&n)));
+        }
+        if let Some(bit) = self.bit {
+            return Some(MappedValue::Pressed(n & (1 << bit) != 0));
+        }
+        Some(MappedValue::Value(n))
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.bits == 0 || self.bits > 32 {
+            return Err(Error::InvalidMapping(format!(
+                "{}: bits must be between 1 and 32",
+                self.name
+            )));
+        }
+        if let Some(bit) = self.bit {
+            if bit as u32 >= self.bits {
+                return Err(Error::InvalidMapping(format!(
+                    "{}: bit {} is outside the {} bit field",
+                    self.name, bit, self.bits
+                )));
+            }
+        }
+        if self.bit_offset as u64 + self.bits as u64 > USB_INPUT_REPORT_SIZE as u64 * 8 {
+            return Err(Error::InvalidMapping(format!(
+                "{}: bits {} to {} are past the end of the report",
+                self.name,
+                self.bit_offset,
+                self.bit_offset as u64 + self.bits as u64 - 1
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MappedValue {
+    Pressed(bool),
+    Value(u32),
+}
+
+/// The fields of one controller variant.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ControllerMapping {
+    pub name: String,
+    pub buttons: Vec<ButtonMapping>,
+}
+
+impl ControllerMapping {
+    /// The built-in DualSense layout from `mappings/dualsense.toml`.
+    pub fn dualsense() -> Self {
+        Self::from_toml_str(DUALSENSE_MAPPING).expect("built-in mapping is valid")
+    }
+
+    pub fn from_toml_str(s: &str) -> Result<Self> {
+        let mapping: Self = toml::from_str(s).map_err(|e| Error::InvalidMapping(e.to_string()))?;
+        mapping.validate()?;
+        Ok(mapping)
+    }
+
+    pub fn from_json_str(s: &str) -> Result<Self> {
+        let mapping: Self =
+            serde_json::from_str(s).map_err(|e| Error::InvalidMapping(e.to_string()))?;
+        mapping.validate()?;
+        Ok(mapping)
+    }
+
+    /// Loads a mapping, choosing the format from the `.toml` or `.json`
+    /// extension.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path)?;
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => Self::from_json_str(&contents),
+            Some("toml") => Self::from_toml_str(&contents),
+            _ => Err(Error::InvalidMapping(format!(
+                "{}: expected a .toml or .json file",
+                path.display()
+            ))),
+        }
+    }
+
+    /// Decodes every field found in `report`, in mapping order.
+    pub fn decode(&self, report: &[u8]) -> MappedState<'_> {
+        MappedState {
+            values: self
+                .buttons
+                .iter()
+                .filter_map(|b| b.decode(report).map(|v| (b.name.as_str(), v)))
+                .collect(),
+        }
+    }
+
+    fn validate(&self) -> Result<()> {
+        self.buttons.iter().try_for_each(ButtonMapping::validate)
+    }
+}
+
+/// Result of decoding a report with a [`ControllerMapping`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MappedState<'a> {
+    pub values: Vec<(&'a str, MappedValue)>,
+}
+
+impl MappedState<'_> {
+    pub fn get(&self, name: &str) -> Option<MappedValue> {
+        self.values
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, v)| *v)
+    }
+
+    /// Whether a button field is pressed. Value fields count as pressed when
+    /// they are non-zero.
+    pub fn is_pressed(&self, name: &str) -> bool {
+        match self.get(name) {
+            Some(MappedValue::Pressed(pressed)) => pressed,
+            Some(MappedValue::Value(n)) => n != 0,
+            None => false,
+        }
+    }
+
+    pub fn value(&self, name: &str) -> Option<u32> {
+        match self.get(name)? {
+            MappedValue::Pressed(pressed) => Some(pressed as u32),
+            MappedValue::Value(n) => Some(n),
+        }
+    }
+}
+
+/// Reads `bits` bits starting at `bit_offset`, little-endian across bytes.
+fn extract_bits(report: &[u8], bit_offset: usize, bits: u32) -> Option<u32> {
+    let end = bit_offset.checked_add(bits as usize)?;
+    if bits == 0 || bits > 32 || end > report.len() * 8 {
+        return None;
+    }
+
+    let first = bit_offset / 8;
+    let last = (end - 1) / 8;
+    let n = report[first..=last]
+        .iter()
+        .enumerate()
+        .fold(0u64, |n, (i, &b)| n | (b as u64) << (8 * i));
+    Some(((n >> (bit_offset % 8)) & ((1u64 << bits) - 1)) as u32)
+}
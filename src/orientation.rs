@@ -0,0 +1,370 @@
+//! Orientation estimation from the motion sensors.
+//!
+//! [`OrientationFilter`] fuses calibrated [`MotionSample`]s with either the
+//! Madgwick or the Mahony filter. The gyro is integrated between reports,
+//! using the sensor clock for the time step, and the accelerometer slowly
+//! pulls the estimate towards gravity to cancel the gyro's drift in roll and
+//! pitch. Yaw has no absolute reference and drifts slowly.
+//!
+//! Angles are given for the controller's body: x points forward, away from
+//! the player, y to the left and z up out of the face, so a controller lying
+//! flat is level. Roll tilts it sideways, pitch tips the front down and yaw
+//! turns it left. The orientation rotates this body frame into a world frame
+//! whose z axis points up, away from gravity.
+
+use std::ops::Mul;
+
+use crate::imu::MotionSample;
+
+/// Gaps between samples longer than this are not integrated, e.g. after
+/// lost reports or a pause in reading.
+const MAX_STEP_SECONDS: f32 = 0.1;
+
+/// The sensor clock wraps when its 1/3 µs tick count overflows 32 bits.
+const TIMESTAMP_WRAP_US: u64 = (u32::MAX as u64 + 1) / 3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quaternion {
+    pub w: f32,
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+impl Default for Quaternion {
+    fn default() -> Self {
+        Quaternion::IDENTITY
+    }
+}
+
+impl Quaternion {
+    pub const IDENTITY: Quaternion = Quaternion {
+        w: 1.0,
+        x: 0.0,
+        y: 0.0,
+        z: 0.0,
+    };
+
+    pub fn new(w: f32, x: f32, y: f32, z: f32) -> Self {
+        Quaternion { w, x, y, z }
+    }
+
+    /// Rotation from roll around x, then pitch around y, then yaw around z,
+    /// all in degrees.
+    pub fn from_euler(angles: EulerAngles) -> Self {
+        let (sr, cr) = (angles.roll.to_radians() / 2.0).sin_cos();
+        let (sp, cp) = (angles.pitch.to_radians() / 2.0).sin_cos();
+        let (sy, cy) = (angles.yaw.to_radians() / 2.0).sin_cos();
+        Quaternion {
+            w: cr * cp * cy + sr * sp * sy,
+            x: sr * cp * cy - cr * sp * sy,
+            y: cr * sp * cy + sr * cp * sy,
+            z: cr * cp * sy - sr * sp * cy,
+        }
+    }
+
+    pub fn to_euler(self) -> EulerAngles {
+        let Quaternion { w, x, y, z } = self;
+        EulerAngles {
+            roll: (2.0 * (w * x + y * z))
+                .atan2(1.0 - 2.0 * (x * x + y * y))
+                .to_degrees(),
+            pitch: (2.0 * (w * y - z * x)).clamp(-1.0, 1.0).asin().to_degrees(),
+            yaw: (2.0 * (w * z + x * y))
+                .atan2(1.0 - 2.0 * (y * y + z * z))
+                .to_degrees(),
+        }
+    }
+
+    pub fn conjugate(self) -> Self {
+        Quaternion::new(self.w, -self.x, -self.y, -self.z)
+    }
+
+    pub fn normalized(self) -> Self {
+        let norm = (self.w * self.w + self.x * self.x + self.y * self.y + self.z * self.z).sqrt();
+        if norm == 0.0 {
+            return Quaternion::IDENTITY;
+        }
+        Quaternion::new(self.w / norm, self.x / norm, self.y / norm, self.z / norm)
+    }
+
+    /// Angle of the rotation in degrees, 0 to 180.
+    pub fn angle(self) -> f32 {
+        (2.0 * self.w.abs().clamp(0.0, 1.0).acos()).to_degrees()
+    }
+}
+
+/// Hamilton product: `a * b` is the rotation `b` followed by `a`.
+impl Mul for Quaternion {
+    type Output = Quaternion;
+
+    fn mul(self, b: Quaternion) -> Quaternion {
+        let a = self;
+        Quaternion {
+            w: a.w * b.w - a.x * b.x - a.y * b.y - a.z * b.z,
+            x: a.w * b.x + a.x * b.w + a.y * b.z - a.z * b.y,
+            y: a.w * b.y - a.x * b.z + a.y * b.w + a.z * b.x,
+            z: a.w * b.z + a.x * b.y - a.y * b.x + a.z * b.w,
+        }
+    }
+}
+
+/// Orientation as angles in degrees, applied in the order roll (around x),
+/// pitch (around y), yaw (around z).
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct EulerAngles {
+    pub roll: f32,
+    pub pitch: f32,
+    pub yaw: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FusionAlgorithm {
+    /// Gradient descent towards gravity, `beta` in rad/s. Higher values
+    /// trust the accelerometer more and correct faster but pass on more of
+    /// its noise.
+    Madgwick { beta: f32 },
+    /// Proportional and integral feedback towards gravity. `kp` works like
+    /// Madgwick's `beta`, `ki` also learns and cancels a constant gyro bias.
+    Mahony { kp: f32, ki: f32 },
+}
+
+impl Default for FusionAlgorithm {
+    fn default() -> Self {
+        FusionAlgorithm::Madgwick { beta: 0.1 }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct OrientationFilter {
+    algorithm: FusionAlgorithm,
+    q: Quaternion,
+    neutral: Quaternion,
+    integral: [f32; 3],
+    last_timestamp_us: Option<u32>,
+}
+
+impl OrientationFilter {
+    pub fn new(algorithm: FusionAlgorithm) -> Self {
+        OrientationFilter {
+            algorithm,
+            ..Default::default()
+        }
+    }
+
+    pub fn madgwick(beta: f32) -> Self {
+        Self::new(FusionAlgorithm::Madgwick { beta })
+    }
+
+    pub fn mahony(kp: f32, ki: f32) -> Self {
+        Self::new(FusionAlgorithm::Mahony { kp, ki })
+    }
+
+    pub fn algorithm(&self) -> FusionAlgorithm {
+        self.algorithm
+    }
+
+    /// Changes the algorithm or its gains, keeping the current estimate.
+    pub fn set_algorithm(&mut self, algorithm: FusionAlgorithm) {
+        self.algorithm = algorithm;
+        self.integral = [0.0; 3];
+    }
+
+    /// Sets the main gain, `beta` for Madgwick and `kp` for Mahony.
+    pub fn set_gain(&mut self, gain: f32) {
+        match &mut self.algorithm {
+            FusionAlgorithm::Madgwick { beta } => *beta = gain,
+            FusionAlgorithm::Mahony { kp, .. } => *kp = gain,
+        }
+    }
+
+    /// Adds a sample, taking the time step from its sensor timestamp, and
+    /// returns the new orientation.
+    ///
+    /// The first sample after creation or [`OrientationFilter::reset`] only
+    /// aligns roll and pitch with the accelerometer.
+    pub fn update(&mut self, sample: &MotionSample) -> Quaternion {
+        let last = self.last_timestamp_us.replace(sample.timestamp_us);
+        match last {
+            None => self.align(sample.accel),
+            Some(last) => {
+                let elapsed = if sample.timestamp_us >= last {
+                    (sample.timestamp_us - last) as u64
+                } else {
+                    sample.timestamp_us as u64 + TIMESTAMP_WRAP_US - last as u64
+                };
+                let dt = elapsed as f32 / 1_000_000.0;
+                if dt > 0.0 && dt <= MAX_STEP_SECONDS {
+                    self.step(sample, dt);
+                }
+            }
+        }
+        self.orientation()
+    }
+
+    /// Adds a sample with an explicit time step in seconds, ignoring its
+    /// timestamp.
+    pub fn update_with_dt(&mut self, sample: &MotionSample, dt: f32) -> Quaternion {
+        self.last_timestamp_us = Some(sample.timestamp_us);
+        self.step(sample, dt);
+        self.orientation()
+    }
+
+    /// Orientation relative to the neutral pose.
+    pub fn orientation(&self) -> Quaternion {
+        (self.neutral.conjugate() * self.q).normalized()
+    }
+
+    pub fn euler(&self) -> EulerAngles {
+        self.orientation().to_euler()
+    }
+
+    /// Orientation in the world frame, ignoring the neutral pose.
+    pub fn absolute_orientation(&self) -> Quaternion {
+        self.q
+    }
+
+    /// Makes the current pose the neutral one, so [`orientation`] reads as
+    /// identity until the controller moves.
+    ///
+    /// [`orientation`]: OrientationFilter::orientation
+    pub fn reset_to_neutral(&mut self) {
+        self.neutral = self.q;
+    }
+
+    /// Forgets everything; the next sample starts over.
+    pub fn reset(&mut self) {
+        *self = Self::new(self.algorithm);
+    }
+
+    fn align(&mut self, accel: [f32; 3]) {
+        let [ax, ay, az] = to_body(accel);
+        if ax == 0.0 && ay == 0.0 && az == 0.0 {
+            return;
+        }
+        self.q = Quaternion::from_euler(EulerAngles {
+            roll: ay.atan2(az).to_degrees(),
+            pitch: (-ax).atan2((ay * ay + az * az).sqrt()).to_degrees(),
+            yaw: 0.0,
+        });
+    }
+
+    fn step(&mut self, sample: &MotionSample, dt: f32) {
+        let gyro = to_body(sample.gyro).map(f32::to_radians);
+        let accel = normalize(to_body(sample.accel));
+        self.q = match self.algorithm {
+            FusionAlgorithm::Madgwick { beta } => madgwick(self.q, gyro, accel, beta, dt),
+            FusionAlgorithm::Mahony { kp, ki } => {
+                mahony(self.q, gyro, accel, kp, ki, &mut self.integral, dt)
+            }
+        };
+    }
+}
+
+/// The sensors' x axis points right, y up out of the face and z towards the
+/// player.
+fn to_body([x, y, z]: [f32; 3]) -> [f32; 3] {
+    [-z, -x, y]
+}
+
+fn normalize(v: [f32; 3]) -> Option<[f32; 3]> {
+    let norm = (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt();
+    if norm == 0.0 {
+        return None;
+    }
+    Some(v.map(|c| c / norm))
+}
+
+fn madgwick(q: Quaternion, g: [f32; 3], a: Option<[f32; 3]>, beta: f32, dt: f32) -> Quaternion {
+    let Quaternion {
+        w: q0,
+        x: q1,
+        y: q2,
+        z: q3,
+    } = q;
+    let [gx, gy, gz] = g;
+
+    let mut dot = [
+        0.5 * (-q1 * gx - q2 * gy - q3 * gz),
+        0.5 * (q0 * gx + q2 * gz - q3 * gy),
+        0.5 * (q0 * gy - q1 * gz + q3 * gx),
+        0.5 * (q0 * gz + q1 * gy - q2 * gx),
+    ];
+
+    if let Some([ax, ay, az]) = a {
+        // Gradient of the error between measured and estimated gravity.
+        let s = [
+            4.0 * q0 * q2 * q2 + 2.0 * q2 * ax + 4.0 * q0 * q1 * q1 - 2.0 * q1 * ay,
+            4.0 * q1 * q3 * q3 - 2.0 * q3 * ax + 4.0 * q0 * q0 * q1 - 2.0 * q0 * ay - 4.0 * q1
+                + 8.0 * q1 * q1 * q1
+                + 8.0 * q1 * q2 * q2
+                + 4.0 * q1 * az,
+            4.0 * q0 * q0 * q2 + 2.0 * q0 * ax + 4.0 * q2 * q3 * q3 - 2.0 * q3 * ay - 4.0 * q2
+                + 8.0 * q2 * q1 * q1
+                + 8.0 * q2 * q2 * q2
+                + 4.0 * q2 * az,
+            4.0 * q1 * q1 * q3 - 2.0 * q1 * ax + 4.0 * q2 * q2 * q3 - 2.0 * q2 * ay,
+        ];
+        let norm = s.iter().map(|v| v * v).sum::<f32>().sqrt();
+        if norm > 0.0 {
+            for (d, s) in dot.iter_mut().zip(s) {
+                *d -= beta * s / norm;
+            }
+        }
+    }
+
+    Quaternion::new(
+        q0 + dot[0] * dt,
+        q1 + dot[1] * dt,
+        q2 + dot[2] * dt,
+        q3 + dot[3] * dt,
+    )
+    .normalized()
+}
+
+fn mahony(
+    q: Quaternion,
+    g: [f32; 3],
+    a: Option<[f32; 3]>,
+    kp: f32,
+    ki: f32,
+    integral: &mut [f32; 3],
+    dt: f32,
+) -> Quaternion {
+    let Quaternion {
+        w: q0,
+        x: q1,
+        y: q2,
+        z: q3,
+    } = q;
+    let [mut gx, mut gy, mut gz] = g;
+
+    if let Some([ax, ay, az]) = a {
+        // Half the direction of gravity as estimated from `q`.
+        let vx = q1 * q3 - q0 * q2;
+        let vy = q0 * q1 + q2 * q3;
+        let vz = q0 * q0 - 0.5 + q3 * q3;
+        // Error is the cross product between measured and estimated gravity.
+        let error = [ay * vz - az * vy, az * vx - ax * vz, ax * vy - ay * vx];
+
+        if ki > 0.0 {
+            for (i, e) in integral.iter_mut().zip(error) {
+                *i += 2.0 * ki * e * dt;
+            }
+        } else {
+            *integral = [0.0; 3];
+        }
+        gx += integral[0] + 2.0 * kp * error[0];
+        gy += integral[1] + 2.0 * kp * error[1];
+        gz += integral[2] + 2.0 * kp * error[2];
+    }
+
+    let (gx, gy, gz) = (gx * 0.5 * dt, gy * 0.5 * dt, gz * 0.5 * dt);
+    Quaternion::new(
+        q0 - q1 * gx - q2 * gy - q3 * gz,
+        q1 + q0 * gx + q2 * gz - q3 * gy,
+        q2 + q0 * gy - q1 * gz + q3 * gx,
+        q3 + q0 * gz + q1 * gy - q2 * gx,
+    )
+    .normalized()
+}
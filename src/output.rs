@@ -0,0 +1,670 @@
+//! Building the output report that drives rumble, LEDs and triggers.
+//!
+//! Like the input reports, offsets are relative to the first byte after the
+//! report id.
+
+use crate::crc;
+use crate::device::Bus;
+use crate::{Error, Result};
+
+pub const USB_OUTPUT_REPORT_ID: u8 = 0x02;
+pub const USB_OUTPUT_REPORT_SIZE: usize = 48;
+pub const BT_OUTPUT_REPORT_ID: u8 = 0x31;
+pub const BT_OUTPUT_REPORT_SIZE: usize = 78;
+
+/// Second header byte of Bluetooth output reports.
+const BT_OUTPUT_TAG: u8 = 0x10;
+
+const COMMON_SIZE: usize = 47;
+
+const OFFSET_VALID_FLAG0: usize = 0;
+const OFFSET_VALID_FLAG1: usize = 1;
+const OFFSET_MOTOR_RIGHT: usize = 2;
+const OFFSET_MOTOR_LEFT: usize = 3;
+const OFFSET_MUTE_BUTTON_LED: usize = 8;
+const OFFSET_POWER_SAVE_CONTROL: usize = 9;
+const OFFSET_RIGHT_TRIGGER: usize = 10;
+const OFFSET_LEFT_TRIGGER: usize = 21;
+const OFFSET_VALID_FLAG2: usize = 38;
+const OFFSET_LIGHTBAR_SETUP: usize = 41;
+const OFFSET_LED_BRIGHTNESS: usize = 42;
+const OFFSET_PLAYER_LEDS: usize = 43;
+const OFFSET_LIGHTBAR: usize = 44;
+
+const VALID_FLAG0_COMPATIBLE_VIBRATION: u8 = 1 << 0;
+const VALID_FLAG0_HAPTICS_SELECT: u8 = 1 << 1;
+const VALID_FLAG0_RIGHT_TRIGGER: u8 = 1 << 2;
+const VALID_FLAG0_LEFT_TRIGGER: u8 = 1 << 3;
+const VALID_FLAG1_MIC_MUTE_LED_CONTROL: u8 = 1 << 0;
+const VALID_FLAG1_POWER_SAVE_CONTROL: u8 = 1 << 1;
+const VALID_FLAG1_LIGHTBAR_CONTROL: u8 = 1 << 2;
+const VALID_FLAG1_PLAYER_INDICATOR_CONTROL: u8 = 1 << 4;
+const VALID_FLAG2_LED_BRIGHTNESS_CONTROL: u8 = 1 << 0;
+const VALID_FLAG2_LIGHTBAR_SETUP_CONTROL: u8 = 1 << 1;
+const VALID_FLAG2_COMPATIBLE_VIBRATION2: u8 = 1 << 2;
+
+const POWER_SAVE_CONTROL_MIC_MUTE: u8 = 1 << 4;
+
+/// Fades out the LEDs of the firmware's connection animation so the host's
+/// settings show.
+const LIGHTBAR_SETUP_LIGHT_OUT: u8 = 1 << 1;
+
+/// The five player indicator LEDs under the touchpad, bit 0 being the
+/// leftmost.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct PlayerLeds(u8);
+
+impl PlayerLeds {
+    pub const OFF: PlayerLeds = PlayerLeds(0);
+    pub const ALL: PlayerLeds = PlayerLeds(0x1f);
+
+    /// LEDs from the low five bits of `mask`.
+    pub const fn from_bits(mask: u8) -> Self {
+        PlayerLeds(mask & 0x1f)
+    }
+
+    /// The pattern a PS5 shows for players 1 to 4, growing outwards from the
+    /// centre LED. `None` for other numbers.
+    pub fn player(number: u8) -> Option<Self> {
+        let mask = match number {
+            1 => 0b00100,
+            2 => 0b01010,
+            3 => 0b10101,
+            4 => 0b11011,
+            _ => return None,
+        };
+        Some(PlayerLeds(mask))
+    }
+
+    pub fn bits(self) -> u8 {
+        self.0
+    }
+
+    /// Whether LED `index`, 0 to 4 from the left, is lit.
+    pub fn is_on(self, index: u8) -> bool {
+        index < 5 && self.0 & (1 << index) != 0
+    }
+
+    /// Turns LED `index`, 0 to 4 from the left, on or off. Other indices
+    /// are ignored.
+    pub fn set(&mut self, index: u8, on: bool) -> &mut Self {
+        if index < 5 {
+            if on {
+                self.0 |= 1 << index;
+            } else {
+                self.0 &= !(1 << index);
+            }
+        }
+        self
+    }
+}
+
+impl From<u8> for PlayerLeds {
+    fn from(mask: u8) -> Self {
+        PlayerLeds::from_bits(mask)
+    }
+}
+
+/// Brightness of the player indicator LEDs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum LedBrightness {
+    #[default]
+    High,
+    Medium,
+    Low,
+}
+
+impl LedBrightness {
+    fn to_byte(self) -> u8 {
+        match self {
+            LedBrightness::High => 0,
+            LedBrightness::Medium => 1,
+            LedBrightness::Low => 2,
+        }
+    }
+}
+
+/// The orange LED of the microphone mute button.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum MuteLed {
+    #[default]
+    Off,
+    On,
+    Breathing,
+}
+
+impl MuteLed {
+    fn to_byte(self) -> u8 {
+        match self {
+            MuteLed::Off => 0,
+            MuteLed::On => 1,
+            MuteLed::Breathing => 2,
+        }
+    }
+}
+
+impl From<bool> for MuteLed {
+    fn from(on: bool) -> Self {
+        if on {
+            MuteLed::On
+        } else {
+            MuteLed::Off
+        }
+    }
+}
+
+/// Which adaptive trigger an effect applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Trigger {
+    Left,
+    Right,
+}
+
+const TRIGGER_MODE_OFF: u8 = 0x05;
+const TRIGGER_MODE_CONTINUOUS_RESISTANCE: u8 = 0x01;
+const TRIGGER_MODE_SECTION_RESISTANCE: u8 = 0x02;
+const TRIGGER_MODE_FEEDBACK: u8 = 0x21;
+const TRIGGER_MODE_WEAPON: u8 = 0x25;
+const TRIGGER_MODE_VIBRATION: u8 = 0x26;
+
+/// Number of zones the trigger travel is divided into.
+pub const TRIGGER_ZONES: usize = 10;
+
+/// Adaptive trigger effect.
+///
+/// Zones split the trigger travel into ten steps, 0 at rest and 9 fully
+/// pulled. Strengths and amplitudes go from 1 to 8, with 0 turning the effect
+/// off. The resistance modes are the older, simpler ones that take raw 0-255
+/// positions and forces instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TriggerEffect {
+    /// No effect, the trigger moves freely.
+    #[default]
+    Off,
+    /// Resistance of `force` from `start` to the end of the travel.
+    ContinuousResistance { start: u8, force: u8 },
+    /// Resistance of `force` between `start` and `end`.
+    SectionResistance { start: u8, end: u8, force: u8 },
+    /// Resistance of `strength` from zone `position` onwards.
+    Feedback { position: u8, strength: u8 },
+    /// Resistance between zones `start` (2-7) and `end` (up to 8) that gives
+    /// way with a snap, like a trigger breaking.
+    Weapon { start: u8, end: u8, strength: u8 },
+    /// Vibration of `amplitude` at `frequency` Hz from zone `position`
+    /// onwards.
+    Vibration {
+        position: u8,
+        amplitude: u8,
+        frequency: u8,
+    },
+    /// Resistance ramping linearly from `start_strength` at zone `start` to
+    /// `end_strength` at zone `end`, and held from there on. Strengths are
+    /// 1-8.
+    SlopeFeedback {
+        start: u8,
+        end: u8,
+        start_strength: u8,
+        end_strength: u8,
+    },
+    /// A strength per zone, 0 leaving the zone free.
+    MultiplePositionFeedback { strengths: [u8; TRIGGER_ZONES] },
+    /// An amplitude per zone, all vibrating at `frequency` Hz.
+    MultiplePositionVibration {
+        frequency: u8,
+        amplitudes: [u8; TRIGGER_ZONES],
+    },
+}
+
+impl TriggerEffect {
+    /// Checks the parameters against the ranges the controller accepts.
+    pub fn validate(&self) -> Result<()> {
+        match *self {
+            TriggerEffect::Off | TriggerEffect::ContinuousResistance { .. } => Ok(()),
+            TriggerEffect::SectionResistance { start, end, .. } => check(
+                start < end,
+                "section resistance start must be before its end",
+            ),
+            TriggerEffect::Feedback { position, strength } => {
+                check_zone(position)?;
+                check_strength(strength)
+            }
+            TriggerEffect::Weapon {
+                start,
+                end,
+                strength,
+            } => {
+                check(
+                    (2..=7).contains(&start),
+                    "weapon start must be between zones 2 and 7",
+                )?;
+                check(
+                    end > start && end <= 8,
+                    "weapon end must be after its start and at most zone 8",
+                )?;
+                check_strength(strength)
+            }
+            TriggerEffect::Vibration {
+                position,
+                amplitude,
+                ..
+            } => {
+                check_zone(position)?;
+                check_strength(amplitude)
+            }
+            TriggerEffect::SlopeFeedback {
+                start,
+                end,
+                start_strength,
+                end_strength,
+            } => {
+                check(start <= 8, "slope start must be at most zone 8")?;
+                check(
+                    end > start && end <= 9,
+                    "slope end must be after its start and at most zone 9",
+                )?;
+                check(
+                    (1..=8).contains(&start_strength) && (1..=8).contains(&end_strength),
+                    "slope strengths must be between 1 and 8",
+                )
+            }
+            TriggerEffect::MultiplePositionFeedback { strengths } => {
+                strengths.iter().try_for_each(|&s| check_strength(s))
+            }
+            TriggerEffect::MultiplePositionVibration { amplitudes, .. } => {
+                amplitudes.iter().try_for_each(|&a| check_strength(a))
+            }
+        }
+    }
+
+    /// Validates the effect and encodes it into the 11 trigger bytes of the
+    /// output report.
+    pub fn encode(&self) -> Result<[u8; 11]> {
+        self.validate()?;
+
+        let mut bytes = [0; 11];
+        match *self {
+            TriggerEffect::Off => bytes[0] = TRIGGER_MODE_OFF,
+            TriggerEffect::ContinuousResistance { start, force } => {
+                bytes[0] = TRIGGER_MODE_CONTINUOUS_RESISTANCE;
+                bytes[1] = start;
+                bytes[2] = force;
+            }
+            TriggerEffect::SectionResistance { start, end, force } => {
+                bytes[0] = TRIGGER_MODE_SECTION_RESISTANCE;
+                bytes[1] = start;
+                bytes[2] = end;
+                bytes[3] = force;
+            }
+            TriggerEffect::Feedback { position, strength } => {
+                let mut strengths = [0; TRIGGER_ZONES];
+                strengths[position as usize..].fill(strength);
+                return TriggerEffect::MultiplePositionFeedback { strengths }.encode();
+            }
+            TriggerEffect::Weapon {
+                start,
+                end,
+                strength,
+            } => {
+                if strength == 0 {
+                    return TriggerEffect::Off.encode();
+                }
+                let zones = (1u16 << start) | (1u16 << end);
+                bytes[0] = TRIGGER_MODE_WEAPON;
+                bytes[1..3].copy_from_slice(&zones.to_le_bytes());
+                bytes[3] = strength - 1;
+            }
+            TriggerEffect::Vibration {
+                position,
+                amplitude,
+                frequency,
+            } => {
+                let mut amplitudes = [0; TRIGGER_ZONES];
+                amplitudes[position as usize..].fill(amplitude);
+                return TriggerEffect::MultiplePositionVibration {
+                    frequency,
+                    amplitudes,
+                }
+                .encode();
+            }
+            TriggerEffect::SlopeFeedback {
+                start,
+                end,
+                start_strength,
+                end_strength,
+            } => {
+                let slope =
+                    (end_strength as f32 - start_strength as f32) / (end as f32 - start as f32);
+                let mut strengths = [0; TRIGGER_ZONES];
+                for (zone, strength) in strengths.iter_mut().enumerate().skip(start as usize) {
+                    *strength = if zone <= end as usize {
+                        (start_strength as f32 + slope * (zone as f32 - start as f32)).round() as u8
+                    } else {
+                        end_strength
+                    };
+                }
+                return TriggerEffect::MultiplePositionFeedback { strengths }.encode();
+            }
+            TriggerEffect::MultiplePositionFeedback { strengths } => {
+                let Some((active, values)) = pack_zones(&strengths) else {
+                    return TriggerEffect::Off.encode();
+                };
+                bytes[0] = TRIGGER_MODE_FEEDBACK;
+                bytes[1..3].copy_from_slice(&active.to_le_bytes());
+                bytes[3..7].copy_from_slice(&values.to_le_bytes());
+            }
+            TriggerEffect::MultiplePositionVibration {
+                frequency,
+                amplitudes,
+            } => {
+                let Some((active, values)) = pack_zones(&amplitudes).filter(|_| frequency > 0)
+                else {
+                    return TriggerEffect::Off.encode();
+                };
+                bytes[0] = TRIGGER_MODE_VIBRATION;
+                bytes[1..3].copy_from_slice(&active.to_le_bytes());
+                bytes[3..7].copy_from_slice(&values.to_le_bytes());
+                bytes[9] = frequency;
+            }
+        }
+        Ok(bytes)
+    }
+}
+
+/// Packs per-zone strengths into a bit mask of active zones and three bits
+/// per zone holding the strength minus one. `None` if no zone is active.
+fn pack_zones(strengths: &[u8; TRIGGER_ZONES]) -> Option<(u16, u32)> {
+    let mut active = 0u16;
+    let mut values = 0u32;
+    for (zone, &strength) in strengths.iter().enumerate() {
+        if strength > 0 {
+            active |= 1 << zone;
+            values |= ((strength - 1) as u32 & 0x07) << (3 * zone);
+        }
+    }
+    (active != 0).then_some((active, values))
+}
+
+fn check(ok: bool, reason: &'static str) -> Result<()> {
+    if ok {
+        Ok(())
+    } else {
+        Err(Error::InvalidTriggerEffect(reason))
+    }
+}
+
+fn check_zone(zone: u8) -> Result<()> {
+    check(
+        (zone as usize) < TRIGGER_ZONES,
+        "zones must be between 0 and 9",
+    )
+}
+
+fn check_strength(strength: u8) -> Result<()> {
+    check(strength <= 8, "strengths must be between 0 and 8")
+}
+
+/// Output report state. Each setter also raises the valid-flag bits the
+/// controller needs to apply the field, so untouched features keep whatever
+/// the controller is currently doing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct OutputReport {
+    valid_flag0: u8,
+    valid_flag1: u8,
+    valid_flag2: u8,
+    motor_left: u8,
+    motor_right: u8,
+    vibration_v2: bool,
+    mute_button_led: u8,
+    power_save_control: u8,
+    left_trigger: [u8; 11],
+    right_trigger: [u8; 11],
+    lightbar_setup: u8,
+    led_brightness: u8,
+    player_leds: u8,
+    lightbar: [u8; 3],
+}
+
+impl OutputReport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the strength of the heavy left and light right rumble motors.
+    pub fn rumble(&mut self, left: u8, right: u8) -> &mut Self {
+        self.motor_left = left;
+        self.motor_right = right;
+        self.valid_flag0 |= VALID_FLAG0_HAPTICS_SELECT;
+        if self.vibration_v2 {
+            self.valid_flag2 |= VALID_FLAG2_COMPATIBLE_VIBRATION2;
+        } else {
+            self.valid_flag0 |= VALID_FLAG0_COMPATIBLE_VIBRATION;
+        }
+        self
+    }
+
+    /// Firmware 2.24 and later expect rumble to be enabled through the
+    /// second compatible-vibration flag.
+    pub fn vibration_v2(&mut self, enabled: bool) -> &mut Self {
+        self.vibration_v2 = enabled;
+        self
+    }
+
+    pub fn lightbar(&mut self, r: u8, g: u8, b: u8) -> &mut Self {
+        self.lightbar = [r, g, b];
+        self.valid_flag1 |= VALID_FLAG1_LIGHTBAR_CONTROL;
+        self
+    }
+
+    /// Sets the five player indicator LEDs, from a [`PlayerLeds`] or from
+    /// the low five bits of a mask, bit 0 being the leftmost LED.
+    ///
+    /// Also ends the firmware's connection animation like
+    /// [`OutputReport::release_leds`], without which the player LEDs would
+    /// not change.
+    pub fn player_leds(&mut self, leds: impl Into<PlayerLeds>) -> &mut Self {
+        self.player_leds = leds.into().bits();
+        self.valid_flag1 |= VALID_FLAG1_PLAYER_INDICATOR_CONTROL;
+        self.release_leds()
+    }
+
+    /// Shows the standard pattern for player 1 to 4, or turns the player
+    /// LEDs off for any other number.
+    pub fn player(&mut self, number: u8) -> &mut Self {
+        self.player_leds(PlayerLeds::player(number).unwrap_or(PlayerLeds::OFF))
+    }
+
+    pub fn led_brightness(&mut self, brightness: LedBrightness) -> &mut Self {
+        self.led_brightness = brightness.to_byte();
+        self.valid_flag2 |= VALID_FLAG2_LED_BRIGHTNESS_CONTROL;
+        self
+    }
+
+    /// Sets the mute button LED, from a [`MuteLed`] or a plain on/off.
+    pub fn mute_led(&mut self, state: impl Into<MuteLed>) -> &mut Self {
+        self.mute_button_led = state.into().to_byte();
+        self.valid_flag1 |= VALID_FLAG1_MIC_MUTE_LED_CONTROL;
+        self
+    }
+
+    /// Ends the firmware's connection animation. Until a report with this
+    /// has been sent, most firmwares keep blinking the lightbar and player
+    /// LEDs and ignore the values set here. Once per connection is enough,
+    /// see [`DualSense::release_leds`](crate::DualSense::release_leds);
+    /// setting the player LEDs does it as well.
+    pub fn release_leds(&mut self) -> &mut Self {
+        self.lightbar_setup = LIGHTBAR_SETUP_LIGHT_OUT;
+        self.valid_flag2 |= VALID_FLAG2_LIGHTBAR_SETUP_CONTROL;
+        self
+    }
+
+    /// Mutes or unmutes the built-in microphone.
+    pub fn mic_mute(&mut self, muted: bool) -> &mut Self {
+        if muted {
+            self.power_save_control |= POWER_SAVE_CONTROL_MIC_MUTE;
+        } else {
+            self.power_save_control &= !POWER_SAVE_CONTROL_MIC_MUTE;
+        }
+        self.valid_flag1 |= VALID_FLAG1_POWER_SAVE_CONTROL;
+        self
+    }
+
+    /// Sets the effect of one adaptive trigger. Fails without changing the
+    /// report if the effect's parameters are out of range.
+    pub fn trigger_effect(&mut self, trigger: Trigger, effect: TriggerEffect) -> Result<&mut Self> {
+        let bytes = effect.encode()?;
+        match trigger {
+            Trigger::Left => {
+                self.left_trigger = bytes;
+                self.valid_flag0 |= VALID_FLAG0_LEFT_TRIGGER;
+            }
+            Trigger::Right => {
+                self.right_trigger = bytes;
+                self.valid_flag0 |= VALID_FLAG0_RIGHT_TRIGGER;
+            }
+        }
+        Ok(self)
+    }
+
+    /// Serializes to USB output report 0x02, report id included.
+    pub fn to_usb_bytes(&self) -> [u8; USB_OUTPUT_REPORT_SIZE] {
+        let mut report = [0; USB_OUTPUT_REPORT_SIZE];
+        report[0] = USB_OUTPUT_REPORT_ID;
+        self.write_common(&mut report[1..]);
+        report
+    }
+
+    /// Serializes to Bluetooth output report 0x31, report id and checksum
+    /// included. Only the low four bits of `seq` are used; the controller
+    /// expects it to advance by one for every report sent.
+    pub fn to_bluetooth_bytes(&self, seq: u8) -> [u8; BT_OUTPUT_REPORT_SIZE] {
+        let mut report = [0; BT_OUTPUT_REPORT_SIZE];
+        report[0] = BT_OUTPUT_REPORT_ID;
+        report[1] = (seq & 0x0f) << 4;
+        report[2] = BT_OUTPUT_TAG;
+        self.write_common(&mut report[3..]);
+
+        let crc = crc::crc32(crc::SEED_OUTPUT, &report[..BT_OUTPUT_REPORT_SIZE - 4]);
+        report[BT_OUTPUT_REPORT_SIZE - 4..].copy_from_slice(&crc.to_le_bytes());
+        report
+    }
+
+    /// Serializes for the given bus. `seq` is ignored over USB.
+    pub fn to_bytes(&self, bus: Bus, seq: u8) -> Vec<u8> {
+        match bus {
+            Bus::Usb => self.to_usb_bytes().to_vec(),
+            Bus::Bluetooth => self.to_bluetooth_bytes(seq).to_vec(),
+        }
+    }
+
+    fn write_common(&self, data: &mut [u8]) {
+        debug_assert!(data.len() >= COMMON_SIZE);
+
+        data[OFFSET_VALID_FLAG0] = self.valid_flag0;
+        data[OFFSET_VALID_FLAG1] = self.valid_flag1;
+        data[OFFSET_MOTOR_RIGHT] = self.motor_right;
+        data[OFFSET_MOTOR_LEFT] = self.motor_left;
+        data[OFFSET_MUTE_BUTTON_LED] = self.mute_button_led;
+        data[OFFSET_POWER_SAVE_CONTROL] = self.power_save_control;
+        data[OFFSET_RIGHT_TRIGGER..OFFSET_RIGHT_TRIGGER + 11].copy_from_slice(&self.right_trigger);
+        data[OFFSET_LEFT_TRIGGER..OFFSET_LEFT_TRIGGER + 11].copy_from_slice(&self.left_trigger);
+        data[OFFSET_VALID_FLAG2] = self.valid_flag2;
+        data[OFFSET_LIGHTBAR_SETUP] = self.lightbar_setup;
+        data[OFFSET_LED_BRIGHTNESS] = self.led_brightness;
+        data[OFFSET_PLAYER_LEDS] = self.player_leds;
+        data[OFFSET_LIGHTBAR..OFFSET_LIGHTBAR + 3].copy_from_slice(&self.lightbar);
+    }
+}
+
+/// What a controller is doing as a result of the output reports it received.
+///
+/// This is the receiving side of [`OutputReport`]: every field only changes
+/// when the report raises its valid flag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct OutputState {
+    pub motor_left: u8,
+    pub motor_right: u8,
+    pub lightbar: [u8; 3],
+    pub player_leds: u8,
+    pub led_brightness: u8,
+    pub lightbar_setup: u8,
+    pub mute_led: u8,
+    pub mic_muted: bool,
+    pub left_trigger: [u8; 11],
+    pub right_trigger: [u8; 11],
+}
+
+impl OutputState {
+    /// Applies a USB 0x02 or Bluetooth 0x31 output report, report id
+    /// included.
+    pub fn apply(&mut self, report: &[u8]) -> Result<()> {
+        let data = match report.first() {
+            Some(&USB_OUTPUT_REPORT_ID) => {
+                check_len(report, USB_OUTPUT_REPORT_SIZE)?;
+                &report[1..]
+            }
+            Some(&BT_OUTPUT_REPORT_ID) => {
+                check_len(report, BT_OUTPUT_REPORT_SIZE)?;
+                let report = &report[..BT_OUTPUT_REPORT_SIZE];
+                crc::verify(crc::SEED_OUTPUT, report)
+                    .map_err(|(expected, actual)| Error::CrcMismatch { expected, actual })?;
+                &report[3..]
+            }
+            Some(&id) => return Err(Error::UnexpectedReportId(id)),
+            None => {
+                return Err(Error::ReportTooShort {
+                    expected: USB_OUTPUT_REPORT_SIZE,
+                    actual: 0,
+                })
+            }
+        };
+
+        let valid_flag0 = data[OFFSET_VALID_FLAG0];
+        let valid_flag1 = data[OFFSET_VALID_FLAG1];
+        let valid_flag2 = data[OFFSET_VALID_FLAG2];
+
+        if valid_flag0 & VALID_FLAG0_COMPATIBLE_VIBRATION != 0
+            || valid_flag2 & VALID_FLAG2_COMPATIBLE_VIBRATION2 != 0
+        {
+            self.motor_left = data[OFFSET_MOTOR_LEFT];
+            self.motor_right = data[OFFSET_MOTOR_RIGHT];
+        }
+        if valid_flag0 & VALID_FLAG0_RIGHT_TRIGGER != 0 {
+            self.right_trigger
+                .copy_from_slice(&data[OFFSET_RIGHT_TRIGGER..OFFSET_RIGHT_TRIGGER + 11]);
+        }
+        if valid_flag0 & VALID_FLAG0_LEFT_TRIGGER != 0 {
+            self.left_trigger
+                .copy_from_slice(&data[OFFSET_LEFT_TRIGGER..OFFSET_LEFT_TRIGGER + 11]);
+        }
+        if valid_flag1 & VALID_FLAG1_MIC_MUTE_LED_CONTROL != 0 {
+            self.mute_led = data[OFFSET_MUTE_BUTTON_LED];
+        }
+        if valid_flag1 & VALID_FLAG1_POWER_SAVE_CONTROL != 0 {
+            self.mic_muted = data[OFFSET_POWER_SAVE_CONTROL] & POWER_SAVE_CONTROL_MIC_MUTE != 0;
+        }
+        if valid_flag1 & VALID_FLAG1_LIGHTBAR_CONTROL != 0 {
+            self.lightbar
+                .copy_from_slice(&data[OFFSET_LIGHTBAR..OFFSET_LIGHTBAR + 3]);
+        }
+        if valid_flag1 & VALID_FLAG1_PLAYER_INDICATOR_CONTROL != 0 {
+            self.player_leds = data[OFFSET_PLAYER_LEDS];
+        }
+        if valid_flag2 & VALID_FLAG2_LED_BRIGHTNESS_CONTROL != 0 {
+            self.led_brightness = data[OFFSET_LED_BRIGHTNESS];
+        }
+        if valid_flag2 & VALID_FLAG2_LIGHTBAR_SETUP_CONTROL != 0 {
+            self.lightbar_setup = data[OFFSET_LIGHTBAR_SETUP];
+        }
+        Ok(())
+    }
+}
+
+fn check_len(report: &[u8], expected: usize) -> Result<()> {
+    if report.len() < expected {
+        return Err(Error::ReportTooShort {
+            expected,
+            actual: report.len(),
+        });
+    }
+    Ok(())
+}
@@ -0,0 +1,253 @@
+//! Timed rumble patterns.
+//!
+//! A [`RumblePattern`] is a list of [`RumbleSegment`]s played back to back.
+//! [`RumbleScheduler`] plays patterns one after the other on a fixed tick,
+//! writing the motor strengths into an [`OutputReport`], and turns the
+//! motors off once nothing is left to play.
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use crate::clock::{Clock, SystemClock};
+use crate::device::DualSense;
+use crate::output::OutputReport;
+use crate::transport::Transport;
+use crate::Result;
+
+/// A stretch of constant rumble, optionally faded in and out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RumbleSegment {
+    pub duration: Duration,
+    /// Strength of the heavy left motor.
+    pub left: u8,
+    /// Strength of the light right motor.
+    pub right: u8,
+    /// Time to ramp up from zero at the start of the segment.
+    pub attack: Duration,
+    /// Time to ramp down to zero at the end of the segment.
+    pub decay: Duration,
+}
+
+impl RumbleSegment {
+    pub fn new(duration: Duration, left: u8, right: u8) -> Self {
+        RumbleSegment {
+            duration,
+            left,
+            right,
+            attack: Duration::ZERO,
+            decay: Duration::ZERO,
+        }
+    }
+
+    /// Both motors off for `duration`.
+    pub fn pause(duration: Duration) -> Self {
+        Self::new(duration, 0, 0)
+    }
+
+    pub fn with_envelope(mut self, attack: Duration, decay: Duration) -> Self {
+        self.attack = attack;
+        self.decay = decay;
+        self
+    }
+
+    /// Envelope level at `t` into the segment, in [0, 1]. Where attack and
+    /// decay overlap the lower of the two wins.
+    pub fn envelope(&self, t: Duration) -> f32 {
+        let attack = if t < self.attack {
+            t.as_secs_f32() / self.attack.as_secs_f32()
+        } else {
+            1.0
+        };
+        let left = self.duration.saturating_sub(t);
+        let decay = if left < self.decay {
+            left.as_secs_f32() / self.decay.as_secs_f32()
+        } else {
+            1.0
+        };
+        attack.min(decay)
+    }
+
+    /// Left and right strength at `t` into the segment.
+    pub fn strength_at(&self, t: Duration) -> (u8, u8) {
+        let level = self.envelope(t);
+        let scale = |strength: u8| (strength as f32 * level).round() as u8;
+        (scale(self.left), scale(self.right))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct RumblePattern {
+    pub segments: Vec<RumbleSegment>,
+}
+
+impl RumblePattern {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends `segment` to the pattern.
+    pub fn then(mut self, segment: RumbleSegment) -> Self {
+        self.segments.push(segment);
+        self
+    }
+
+    /// `count` pulses of `on` separated by `off`.
+    pub fn repeat(on: RumbleSegment, off: Duration, count: usize) -> Self {
+        let mut pattern = RumblePattern::new();
+        for i in 0..count {
+            if i > 0 {
+                pattern = pattern.then(RumbleSegment::pause(off));
+            }
+            pattern = pattern.then(on);
+        }
+        pattern
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.segments.iter().map(|s| s.duration).sum()
+    }
+
+    /// Left and right strength at `t` into the pattern, `None` once it is
+    /// over.
+    pub fn strength_at(&self, mut t: Duration) -> Option<(u8, u8)> {
+        for segment in &self.segments {
+            if t < segment.duration {
+                return Some(segment.strength_at(t));
+            }
+            t -= segment.duration;
+        }
+        None
+    }
+}
+
+/// Plays [`RumblePattern`]s on a fixed tick.
+pub struct RumbleScheduler<C: Clock = SystemClock> {
+    clock: C,
+    current: Option<(RumblePattern, Duration)>,
+    queue: VecDeque<RumblePattern>,
+    tick: Duration,
+    next_tick: Duration,
+    last: Option<(u8, u8)>,
+}
+
+impl RumbleScheduler<SystemClock> {
+    pub fn new() -> Self {
+        Self::with_clock(SystemClock::new())
+    }
+}
+
+impl Default for RumbleScheduler<SystemClock> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C: Clock> RumbleScheduler<C> {
+    /// Idle scheduler on `clock`, ticking every 16 ms.
+    pub fn with_clock(clock: C) -> Self {
+        let now = clock.now();
+        RumbleScheduler {
+            clock,
+            current: None,
+            queue: VecDeque::new(),
+            tick: Duration::from_millis(16),
+            next_tick: now,
+            last: None,
+        }
+    }
+
+    pub fn set_tick(&mut self, tick: Duration) {
+        self.tick = tick;
+    }
+
+    /// Interrupts whatever is playing, drops the queue and starts `pattern`
+    /// now.
+    pub fn play(&mut self, pattern: RumblePattern) {
+        let now = self.clock.now();
+        self.queue.clear();
+        self.current = Some((pattern, now));
+        self.next_tick = now;
+    }
+
+    /// Plays `pattern` once everything before it has finished, or now if
+    /// nothing is playing.
+    pub fn queue(&mut self, pattern: RumblePattern) {
+        if self.current.is_none() {
+            self.play(pattern);
+        } else {
+            self.queue.push_back(pattern);
+        }
+    }
+
+    /// Stops playing and drops the queue. The motors are turned off on the
+    /// next update.
+    pub fn stop(&mut self) {
+        self.current = None;
+        self.queue.clear();
+        self.next_tick = self.clock.now();
+    }
+
+    /// Whether a pattern is playing, or the motors still have to be turned
+    /// off after one.
+    pub fn is_active(&mut self) -> bool {
+        self.advance(self.clock.now());
+        self.current.is_some() || self.last.is_some_and(|last| last != (0, 0))
+    }
+
+    /// Writes the motor strengths into `report` if a tick is due and returns
+    /// them. Once the last pattern is over the motors are set to zero, after
+    /// which the report's rumble is left alone until the next pattern.
+    pub fn update(&mut self, report: &mut OutputReport) -> Option<(u8, u8)> {
+        let now = self.clock.now();
+        if now < self.next_tick {
+            return None;
+        }
+        while self.next_tick <= now && !self.tick.is_zero() {
+            self.next_tick += self.tick;
+        }
+
+        self.advance(now);
+        let strength = match &self.current {
+            Some((pattern, started)) => pattern.strength_at(now - *started).unwrap_or((0, 0)),
+            None if self.last.is_some_and(|last| last != (0, 0)) => (0, 0),
+            None => return None,
+        };
+        report.rumble(strength.0, strength.1);
+        self.last = Some(strength);
+        Some(strength)
+    }
+
+    /// Time left until the next tick is due.
+    pub fn until_next_tick(&self) -> Duration {
+        self.next_tick.saturating_sub(self.clock.now())
+    }
+
+    /// Sends `report` with the scheduled rumble on every tick until all
+    /// patterns have played and the motors are off.
+    pub fn run<T: Transport>(
+        &mut self,
+        dualsense: &mut DualSense<T>,
+        report: &mut OutputReport,
+    ) -> Result<()> {
+        while self.is_active() {
+            if self.update(report).is_some() {
+                dualsense.send(report)?;
+            }
+            self.clock
+                .sleep(self.until_next_tick().max(Duration::from_micros(100)));
+        }
+        Ok(())
+    }
+
+    /// Moves past finished patterns, starting queued ones where the previous
+    /// one ended so a queue plays without gaps.
+    fn advance(&mut self, now: Duration) {
+        while let Some((pattern, started)) = &self.current {
+            let end = *started + pattern.duration();
+            if now < end {
+                break;
+            }
+            self.current = self.queue.pop_front().map(|pattern| (pattern, end));
+        }
+    }
+}
@@ -0,0 +1,226 @@
+//! A controller that lives in memory, for development and tests on machines
+//! without a DualSense attached.
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use crate::crc;
+use crate::device::{
+    Bus, FEATURE_REPORT_CALIBRATION, FEATURE_REPORT_FIRMWARE_INFO, FEATURE_REPORT_PAIRING_INFO,
+};
+use crate::imu::CALIBRATION_REPORT_SIZE;
+use crate::info::{FIRMWARE_INFO_SIZE, PAIRING_INFO_SIZE};
+use crate::input::{ChargingState, InputState, PowerStatus};
+use crate::output::OutputState;
+use crate::transport::Transport;
+use crate::{Error, Result};
+
+/// Simulated DualSense that behaves like a [`Transport`] to a real one.
+///
+/// Every read produces an input report from the current state, with the
+/// sequence counter and sensor clock advancing as on hardware. States queued
+/// with [`SimulatedDualSense::queue_state`] are played one per report before
+/// falling back to the current state. Output reports are decoded into
+/// [`OutputState`], and feature reports are answered from the public fields.
+#[derive(Debug, Clone)]
+pub struct SimulatedDualSense {
+    /// Controller MAC address, most significant byte first.
+    pub mac: [u8; 6],
+    /// MAC address of the host the controller is paired with.
+    pub host_mac: [u8; 6],
+    pub build_date: String,
+    pub build_time: String,
+    pub hardware_version: u32,
+    pub firmware_version: u32,
+    pub update_version: u16,
+    /// Raw IMU calibration values in feature report 0x05 order: gyro pitch,
+    /// yaw and roll bias, gyro pitch, yaw and roll plus/minus, gyro speed
+    /// plus/minus, then accelerometer x, y and z plus/minus.
+    pub calibration: [i16; 17],
+
+    bus: Bus,
+    state: InputState,
+    script: VecDeque<InputState>,
+    report_interval: Duration,
+    sequence: u8,
+    sensor_timestamp: u32,
+    output_state: OutputState,
+    outputs: Vec<Vec<u8>>,
+    features_written: Vec<Vec<u8>>,
+}
+
+impl SimulatedDualSense {
+    pub fn new(bus: Bus) -> Self {
+        SimulatedDualSense {
+            mac: [0xa0, 0x5a, 0x5e, 0x12, 0x34, 0x56],
+            host_mac: [0x00, 0x1a, 0x7d, 0xda, 0x71, 0x10],
+            build_date: String::from("Jun 15 2021"),
+            build_time: String::from("08:32:03"),
+            hardware_version: 0x0000_0614,
+            firmware_version: 0x0110_002a,
+            update_version: 0x0224,
+            calibration: [
+                -2, 3, 1, // gyro bias
+                8870, -8873, // gyro pitch plus/minus
+                8860, -8868, // gyro yaw plus/minus
+                8884, -8880, // gyro roll plus/minus
+                540, 540, // gyro speed plus/minus
+                8212, -8180, // accel x plus/minus
+                8190, -8202, // accel y plus/minus
+                8240, -8120, // accel z plus/minus
+            ],
+            bus,
+            state: InputState {
+                power: PowerStatus {
+                    level: 8,
+                    charging: ChargingState::Discharging,
+                },
+                ..Default::default()
+            },
+            script: VecDeque::new(),
+            report_interval: Duration::from_millis(4),
+            sequence: 0,
+            sensor_timestamp: 0,
+            output_state: OutputState::default(),
+            outputs: Vec::new(),
+            features_written: Vec::new(),
+        }
+    }
+
+    /// State reported once the queued states have been played.
+    pub fn state(&self) -> &InputState {
+        &self.state
+    }
+
+    pub fn state_mut(&mut self) -> &mut InputState {
+        &mut self.state
+    }
+
+    pub fn set_state(&mut self, state: InputState) {
+        self.state = state;
+    }
+
+    /// Queues a state to be reported once. The last queued state becomes the
+    /// current state after it has been played.
+    pub fn queue_state(&mut self, state: InputState) {
+        self.script.push_back(state);
+    }
+
+    pub fn queued_states(&self) -> usize {
+        self.script.len()
+    }
+
+    /// Time between two reports as seen by the sensor clock. Defaults to
+    /// 4 ms, the controller's 250 Hz report rate.
+    pub fn set_report_interval(&mut self, interval: Duration) {
+        self.report_interval = interval;
+    }
+
+    /// Rumble, LEDs and triggers as set by the output reports written so far.
+    pub fn output_state(&self) -> &OutputState {
+        &self.output_state
+    }
+
+    /// Raw output reports written so far, oldest first.
+    pub fn outputs(&self) -> &[Vec<u8>] {
+        &self.outputs
+    }
+
+    /// Feature reports written so far, oldest first.
+    pub fn features_written(&self) -> &[Vec<u8>] {
+        &self.features_written
+    }
+
+    /// Produces the next input report for the current bus.
+    pub fn next_report(&mut self) -> Vec<u8> {
+        if let Some(state) = self.script.pop_front() {
+            self.state = state;
+        }
+        self.sequence = self.sequence.wrapping_add(1);
+        self.sensor_timestamp = self
+            .sensor_timestamp
+            .wrapping_add((self.report_interval.as_micros() * 3) as u32);
+
+        let mut state = self.state;
+        state.sequence = self.sequence;
+        state.sensor_timestamp = self.sensor_timestamp;
+        state.to_bytes(self.bus)
+    }
+
+    fn feature_report(&self, report_id: u8) -> Option<Vec<u8>> {
+        let mut report = match report_id {
+            FEATURE_REPORT_CALIBRATION => {
+                let mut report = vec![0; CALIBRATION_REPORT_SIZE];
+                for (i, value) in self.calibration.iter().enumerate() {
+                    report[1 + i * 2..3 + i * 2].copy_from_slice(&value.to_le_bytes());
+                }
+                report
+            }
+            FEATURE_REPORT_PAIRING_INFO => {
+                let mut report = vec![0; PAIRING_INFO_SIZE];
+                // Both addresses are stored least significant byte first.
+                for i in 0..6 {
+                    report[1 + i] = self.mac[5 - i];
+                    report[10 + i] = self.host_mac[5 - i];
+                }
+                report
+            }
+            FEATURE_REPORT_FIRMWARE_INFO => {
+                let mut report = vec![0; FIRMWARE_INFO_SIZE];
+                copy_str(&mut report[1..12], &self.build_date);
+                copy_str(&mut report[12..20], &self.build_time);
+                report[24..28].copy_from_slice(&self.hardware_version.to_le_bytes());
+                report[28..32].copy_from_slice(&self.firmware_version.to_le_bytes());
+                report[44..46].copy_from_slice(&self.update_version.to_le_bytes());
+                report
+            }
+            _ => return None,
+        };
+
+        report[0] = report_id;
+        if self.bus == Bus::Bluetooth {
+            let len = report.len();
+            let crc = crc::crc32(crc::SEED_FEATURE, &report[..len - 4]);
+            report[len - 4..].copy_from_slice(&crc.to_le_bytes());
+        }
+        Some(report)
+    }
+}
+
+impl Transport for SimulatedDualSense {
+    fn bus(&self) -> Bus {
+        self.bus
+    }
+
+    fn read_input(&mut self, buf: &mut [u8], _timeout: Duration) -> Result<usize> {
+        let report = self.next_report();
+        let len = report.len().min(buf.len());
+        buf[..len].copy_from_slice(&report[..len]);
+        Ok(len)
+    }
+
+    fn write_output(&mut self, report: &[u8]) -> Result<usize> {
+        self.output_state.apply(report)?;
+        self.outputs.push(report.to_vec());
+        Ok(report.len())
+    }
+
+    fn get_feature(&mut self, report_id: u8, buf: &mut [u8]) -> Result<usize> {
+        let report = self
+            .feature_report(report_id)
+            .ok_or(Error::UnsupportedReport(report_id))?;
+        let len = report.len().min(buf.len());
+        buf[..len].copy_from_slice(&report[..len]);
+        Ok(len)
+    }
+
+    fn set_feature(&mut self, report: &[u8]) -> Result<()> {
+        self.features_written.push(report.to_vec());
+        Ok(())
+    }
+}
+
+fn copy_str(dst: &mut [u8], s: &str) {
+    let len = s.len().min(dst.len());
+    dst[..len].copy_from_slice(&s.as_bytes()[..len]);
+}
@@ -0,0 +1,175 @@
+//! Deadzones and response curves for the analog sticks.
+//!
+//! A [`StickConfig`] turns a raw [`Stick`] into a [`StickPosition`] in
+//! [-1, 1]. Processing runs in this order: inner deadzone and outer deadzone,
+//! response curve, then anti-deadzone.
+
+use crate::input::{InputState, Stick};
+
+/// Inner deadzone, as a fraction of full deflection.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Deadzone {
+    None,
+    /// Each axis is cut off on its own, which keeps straight lines easy to
+    /// hit but makes diagonals snap to the axes near the centre.
+    Axial(f32),
+    /// Positions inside the circle are zeroed, the rest pass unchanged, so
+    /// output jumps from 0 to the deadzone size at the edge.
+    Radial(f32),
+    /// Like `Radial`, but the remaining range is rescaled so output starts
+    /// at 0 at the edge of the deadzone.
+    ScaledRadial(f32),
+}
+
+impl Deadzone {
+    /// The same deadzone with its size clamped to [0, 1]. A negative size
+    /// would let the centre through, or divide by a zero magnitude.
+    fn clamped(self) -> Self {
+        match self {
+            Deadzone::None => Deadzone::None,
+            Deadzone::Axial(size) => Deadzone::Axial(size.clamp(0.0, 1.0)),
+            Deadzone::Radial(size) => Deadzone::Radial(size.clamp(0.0, 1.0)),
+            Deadzone::ScaledRadial(size) => Deadzone::ScaledRadial(size.clamp(0.0, 1.0)),
+        }
+    }
+}
+
+/// Mapping of the deflection after the deadzones, from [0, 1] to [0, 1].
+#[derive(Debug, Clone, Copy)]
+pub enum ResponseCurve {
+    Linear,
+    /// `x.powf(exponent)`; exponents above 1 give finer control near the
+    /// centre.
+    Exponential(f32),
+    /// Any function; its output is clamped to [0, 1].
+    Custom(fn(f32) -> f32),
+}
+
+impl ResponseCurve {
+    pub fn apply(&self, value: f32) -> f32 {
+        let value = value.clamp(0.0, 1.0);
+        match self {
+            ResponseCurve::Linear => value,
+            ResponseCurve::Exponential(exponent) => value.powf(*exponent),
+            ResponseCurve::Custom(f) => f(value).clamp(0.0, 1.0),
+        }
+    }
+}
+
+/// Processed stick position, both axes in [-1, 1], negative is left/up
+/// unless the y axis is inverted.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct StickPosition {
+    pub x: f32,
+    pub y: f32,
+}
+
+impl StickPosition {
+    pub fn magnitude(&self) -> f32 {
+        self.x.hypot(self.y).min(1.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct StickConfig {
+    pub deadzone: Deadzone,
+    /// Deflection past which the output is full, 1.0 to disable. Sticks
+    /// rarely reach the corners of the raw range, so a value a little below
+    /// 1 makes full output reachable.
+    pub outer_deadzone: f32,
+    /// Smallest output magnitude outside the deadzone, to overcome a game's
+    /// own deadzone.
+    pub anti_deadzone: f32,
+    pub curve: ResponseCurve,
+    /// Makes up positive.
+    pub invert_y: bool,
+}
+
+impl Default for StickConfig {
+    fn default() -> Self {
+        StickConfig {
+            deadzone: Deadzone::ScaledRadial(0.1),
+            outer_deadzone: 0.95,
+            anti_deadzone: 0.0,
+            curve: ResponseCurve::Linear,
+            invert_y: false,
+        }
+    }
+}
+
+impl StickConfig {
+    /// Config that only normalizes, without any deadzone or curve.
+    pub fn raw() -> Self {
+        StickConfig {
+            deadzone: Deadzone::None,
+            outer_deadzone: 1.0,
+            anti_deadzone: 0.0,
+            curve: ResponseCurve::Linear,
+            invert_y: false,
+        }
+    }
+
+    pub fn process(&self, stick: Stick) -> StickPosition {
+        let (x, y) = stick.normalized();
+        let y = if self.invert_y { -y } else { y };
+
+        let magnitude = x.hypot(y);
+        let scaled = match self.deadzone.clamped() {
+            Deadzone::Axial(inner) => {
+                return StickPosition {
+                    x: self.shape(rescale(x.abs(), inner, self.outer_deadzone)) * x.signum(),
+                    y: self.shape(rescale(y.abs(), inner, self.outer_deadzone)) * y.signum(),
+                };
+            }
+            Deadzone::Radial(inner) if magnitude < inner => 0.0,
+            Deadzone::Radial(_) | Deadzone::None => rescale(magnitude, 0.0, self.outer_deadzone),
+            Deadzone::ScaledRadial(inner) => rescale(magnitude, inner, self.outer_deadzone),
+        };
+        if scaled == 0.0 {
+            return StickPosition::default();
+        }
+
+        let factor = self.shape(scaled) / magnitude;
+        StickPosition {
+            x: (x * factor).clamp(-1.0, 1.0),
+            y: (y * factor).clamp(-1.0, 1.0),
+        }
+    }
+
+    /// Response curve and anti-deadzone for a deflection in [0, 1].
+    fn shape(&self, value: f32) -> f32 {
+        if value == 0.0 {
+            return 0.0;
+        }
+        let anti = self.anti_deadzone.clamp(0.0, 1.0);
+        anti + (1.0 - anti) * self.curve.apply(value)
+    }
+}
+
+/// Configs for both sticks of a controller.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StickProcessor {
+    pub left: StickConfig,
+    pub right: StickConfig,
+}
+
+impl StickProcessor {
+    /// Left and right stick positions of `state`.
+    pub fn process(&self, state: &InputState) -> (StickPosition, StickPosition) {
+        (
+            self.left.process(state.left_stick),
+            self.right.process(state.right_stick),
+        )
+    }
+}
+
+/// Maps `value` from [inner, outer] to [0, 1], clamping outside the range.
+fn rescale(value: f32, inner: f32, outer: f32) -> f32 {
+    if value <= inner {
+        return 0.0;
+    }
+    if outer <= inner {
+        return 1.0;
+    }
+    ((value - inner) / (outer - inner)).min(1.0)
+}
@@ -0,0 +1,109 @@
+//! Finger tracking on the touchpad.
+//!
+//! The touchpad reports two contacts per input report. A contact keeps its
+//! 7-bit tracking id while the finger stays down, but may change slots when
+//! the other finger lifts, and the id wraps after 128 touches.
+//! [`FingerTracker`] follows contacts by tracking id and gives every touch a
+//! [`Finger`] id that is never reused.
+
+use crate::input::InputState;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Finger {
+    /// Stable id of this touch, unique for the lifetime of the tracker.
+    pub id: u32,
+    /// Tracking id the controller assigned to the contact.
+    pub tracking_id: u8,
+    pub x: u16,
+    pub y: u16,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TouchEvent {
+    /// A finger touched the pad.
+    Down(Finger),
+    /// A finger moved; `previous` is where it was in the last report.
+    Move { finger: Finger, previous: Finger },
+    /// A finger lifted, at its last known position.
+    Up(Finger),
+}
+
+impl TouchEvent {
+    pub fn finger(&self) -> Finger {
+        match *self {
+            TouchEvent::Down(finger) | TouchEvent::Up(finger) => finger,
+            TouchEvent::Move { finger, .. } => finger,
+        }
+    }
+}
+
+/// Turns the touchpad contacts of consecutive input states into
+/// [`TouchEvent`]s.
+#[derive(Debug, Clone, Default)]
+pub struct FingerTracker {
+    fingers: Vec<Finger>,
+    next_id: u32,
+}
+
+impl FingerTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fingers currently on the pad, oldest first.
+    pub fn fingers(&self) -> &[Finger] {
+        &self.fingers
+    }
+
+    /// Compares the contacts of `state` with the fingers down so far and
+    /// returns the events, in the order lifts, moves, touches.
+    pub fn update(&mut self, state: &InputState) -> Vec<TouchEvent> {
+        let contacts: Vec<_> = state.touch.iter().filter(|c| c.active).collect();
+        let mut events = Vec::new();
+
+        self.fingers.retain(|finger| {
+            let down = contacts.iter().any(|c| c.id == finger.tracking_id);
+            if !down {
+                events.push(TouchEvent::Up(*finger));
+            }
+            down
+        });
+
+        for finger in &mut self.fingers {
+            let Some(contact) = contacts.iter().find(|c| c.id == finger.tracking_id) else {
+                continue;
+            };
+            if (contact.x, contact.y) != (finger.x, finger.y) {
+                let previous = *finger;
+                finger.x = contact.x;
+                finger.y = contact.y;
+                events.push(TouchEvent::Move {
+                    finger: *finger,
+                    previous,
+                });
+            }
+        }
+
+        for contact in contacts {
+            if self.fingers.iter().any(|f| f.tracking_id == contact.id) {
+                continue;
+            }
+            let finger = Finger {
+                id: self.next_id,
+                tracking_id: contact.id,
+                x: contact.x,
+                y: contact.y,
+            };
+            self.next_id = self.next_id.wrapping_add(1);
+            self.fingers.push(finger);
+            events.push(TouchEvent::Down(finger));
+        }
+
+        events
+    }
+
+    /// Forgets the fingers down without reporting them as lifted.
+    pub fn reset(&mut self) {
+        self.fingers.clear();
+    }
+}
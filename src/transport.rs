@@ -0,0 +1,58 @@
+//! Ways of exchanging reports with a controller.
+//!
+//! [`DualSense`](crate::DualSense) only talks to a [`Transport`], so the same
+//! controller logic runs on libusb, on Linux hidraw or against an in-memory
+//! mock.
+
+use std::time::Duration;
+
+use crate::device::Bus;
+use crate::Result;
+
+#[cfg(target_os = "linux")]
+pub mod hidraw;
+pub mod mock;
+pub mod usb;
+
+pub trait Transport {
+    /// The bus the controller is connected through, which decides the report
+    /// layouts.
+    fn bus(&self) -> Bus;
+
+    /// Reads one input report, report id included, and returns its length.
+    /// Returns [`Error::Timeout`](crate::Error::Timeout) if none arrives
+    /// within `timeout`.
+    fn read_input(&mut self, buf: &mut [u8], timeout: Duration) -> Result<usize>;
+
+    /// Writes one output report, report id included.
+    fn write_output(&mut self, report: &[u8]) -> Result<usize>;
+
+    /// Reads feature report `report_id` into `buf` and returns its length.
+    /// The first byte of `buf` is the report id.
+    fn get_feature(&mut self, report_id: u8, buf: &mut [u8]) -> Result<usize>;
+
+    /// Writes a feature report whose first byte is the report id.
+    fn set_feature(&mut self, report: &[u8]) -> Result<()>;
+}
+
+impl<T: Transport + ?Sized> Transport for Box<T> {
+    fn bus(&self) -> Bus {
+        (**self).bus()
+    }
+
+    fn read_input(&mut self, buf: &mut [u8], timeout: Duration) -> Result<usize> {
+        (**self).read_input(buf, timeout)
+    }
+
+    fn write_output(&mut self, report: &[u8]) -> Result<usize> {
+        (**self).write_output(report)
+    }
+
+    fn get_feature(&mut self, report_id: u8, buf: &mut [u8]) -> Result<usize> {
+        (**self).get_feature(report_id, buf)
+    }
+
+    fn set_feature(&mut self, report: &[u8]) -> Result<()> {
+        (**self).set_feature(report)
+    }
+}
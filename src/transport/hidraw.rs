@@ -0,0 +1,222 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Write};
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::device::{Bus, DUALSENSE_PRODUCT_ID, FEATURE_REPORT_CALIBRATION, SONY_VENDOR_ID};
+use crate::imu::CALIBRATION_REPORT_SIZE;
+use crate::transport::Transport;
+use crate::{Error, Result};
+
+const SYSFS_HIDRAW: &str = "/sys/class/hidraw";
+
+const HID_BUS_USB: u16 = 0x03;
+const HID_BUS_BLUETOOTH: u16 = 0x05;
+
+const HIDIOCSFEATURE_NR: u8 = 0x06;
+const HIDIOCGFEATURE_NR: u8 = 0x07;
+
+/// A hidraw node as described by sysfs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HidrawDevice {
+    /// Device node, e.g. `/dev/hidraw3`.
+    pub path: PathBuf,
+    pub bus: Bus,
+    pub vendor_id: u16,
+    pub product_id: u16,
+    /// `HID_NAME` from sysfs.
+    pub name: String,
+    /// `HID_UNIQ` from sysfs. For Sony controllers the driver sets it to the
+    /// controller's MAC address on both buses; other devices often leave it
+    /// empty.
+    pub uniq: String,
+    /// `HID_PHYS` from sysfs: the USB port path or the host adapter's MAC.
+    pub phys: String,
+}
+
+/// Lists the hidraw nodes on USB or Bluetooth matching `vid`/`pid`.
+pub fn find_devices(vid: u16, pid: u16) -> Result<Vec<HidrawDevice>> {
+    let mut devices = list_devices()?;
+    devices.retain(|device| device.vendor_id == vid && device.product_id == pid);
+    Ok(devices)
+}
+
+/// Lists all hidraw nodes on USB or Bluetooth, sorted by path.
+pub fn list_devices() -> Result<Vec<HidrawDevice>> {
+    let mut devices = Vec::new();
+
+    for entry in fs::read_dir(SYSFS_HIDRAW)? {
+        let entry = entry?;
+        let uevent = match fs::read_to_string(entry.path().join("device/uevent")) {
+            Ok(uevent) => uevent,
+            Err(_) => continue,
+        };
+        let device = match parse_uevent(&uevent, Path::new("/dev").join(entry.file_name())) {
+            Some(device) => device,
+            None => continue,
+        };
+        devices.push(device);
+    }
+
+    devices.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(devices)
+}
+
+/// Parses the `device/uevent` file of a hidraw node at `path`. Returns
+/// `None` if it lacks a `HID_ID` or the device is on neither USB nor
+/// Bluetooth.
+pub fn parse_uevent(uevent: &str, path: PathBuf) -> Option<HidrawDevice> {
+    let mut hid_id = None;
+    let mut name = String::new();
+    let mut uniq = String::new();
+    let mut phys = String::new();
+
+    for line in uevent.lines() {
+        match line.split_once('=') {
+            Some(("HID_ID", value)) => hid_id = Some(value),
+            Some(("HID_NAME", value)) => name = value.to_string(),
+            Some(("HID_UNIQ", value)) => uniq = value.to_string(),
+            Some(("HID_PHYS", value)) => phys = value.to_string(),
+            _ => (),
+        }
+    }
+
+    // HID_ID=0003:0000054C:00000CE6
+    let mut parts = hid_id?.split(':');
+    let bus = u16::from_str_radix(parts.next()?, 16).ok()?;
+    let vendor_id = u32::from_str_radix(parts.next()?, 16).ok()?;
+    let product_id = u32::from_str_radix(parts.next()?, 16).ok()?;
+
+    let bus = match bus {
+        HID_BUS_USB => Bus::Usb,
+        HID_BUS_BLUETOOTH => Bus::Bluetooth,
+        _ => return None,
+    };
+
+    Some(HidrawDevice {
+        path,
+        bus,
+        vendor_id: vendor_id as u16,
+        product_id: product_id as u16,
+        name,
+        uniq,
+        phys,
+    })
+}
+
+/// Transport over a Linux `/dev/hidrawN` node.
+///
+/// Unlike [`UsbTransport`](crate::transport::usb::UsbTransport) this leaves
+/// the kernel driver bound, so the controller keeps working as a gamepad for
+/// the rest of the system, and it works over Bluetooth as well as USB.
+pub struct HidrawTransport {
+    file: File,
+    device: HidrawDevice,
+}
+
+impl HidrawTransport {
+    /// Opens the first DualSense found through sysfs.
+    pub fn open() -> Result<Self> {
+        let device = find_devices(SONY_VENDOR_ID, DUALSENSE_PRODUCT_ID)?
+            .into_iter()
+            .next()
+            .ok_or(Error::DeviceNotFound {
+                vid: SONY_VENDOR_ID,
+                pid: DUALSENSE_PRODUCT_ID,
+            })?;
+        Self::open_device(device)
+    }
+
+    pub fn open_device(device: HidrawDevice) -> Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(&device.path)?;
+        let mut transport = HidrawTransport { file, device };
+
+        // Reading the calibration switches a Bluetooth controller from the
+        // short 0x01 input reports to the extended 0x31 ones.
+        if transport.device.bus == Bus::Bluetooth {
+            let mut buf = [0; CALIBRATION_REPORT_SIZE];
+            transport
+                .get_feature(FEATURE_REPORT_CALIBRATION, &mut buf)
+                .ok();
+        }
+
+        Ok(transport)
+    }
+
+    pub fn device(&self) -> &HidrawDevice {
+        &self.device
+    }
+}
+
+impl Transport for HidrawTransport {
+    fn bus(&self) -> Bus {
+        self.device.bus
+    }
+
+    fn read_input(&mut self, buf: &mut [u8], timeout: Duration) -> Result<usize> {
+        let mut pollfd = libc::pollfd {
+            fd: self.file.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        let timeout_ms = timeout.as_millis().min(i32::MAX as u128) as i32;
+        match unsafe { libc::poll(&mut pollfd, 1, timeout_ms) } {
+            n if n < 0 => return Err(std::io::Error::last_os_error().into()),
+            0 => return Err(Error::Timeout),
+            _ => (),
+        }
+        Ok(self.file.read(buf)?)
+    }
+
+    fn write_output(&mut self, report: &[u8]) -> Result<usize> {
+        Ok(self.file.write(report)?)
+    }
+
+    fn get_feature(&mut self, report_id: u8, buf: &mut [u8]) -> Result<usize> {
+        if buf.is_empty() {
+            return Err(Error::ReportTooShort {
+                expected: 1,
+                actual: 0,
+            });
+        }
+        buf[0] = report_id;
+        let n = unsafe {
+            libc::ioctl(
+                self.file.as_raw_fd(),
+                hidioc_feature(HIDIOCGFEATURE_NR, buf.len()) as _,
+                buf.as_mut_ptr(),
+            )
+        };
+        if n < 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        Ok(n as usize)
+    }
+
+    fn set_feature(&mut self, report: &[u8]) -> Result<()> {
+        let n = unsafe {
+            libc::ioctl(
+                self.file.as_raw_fd(),
+                hidioc_feature(HIDIOCSFEATURE_NR, report.len()) as _,
+                report.as_ptr(),
+            )
+        };
+        if n < 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        Ok(())
+    }
+}
+
+/// `_IOC(_IOC_WRITE | _IOC_READ, 'H', nr, len)` from `linux/hidraw.h`.
+fn hidioc_feature(nr: u8, len: usize) -> libc::c_ulong {
+    const IOC_READ_WRITE: libc::c_ulong = 3;
+    (IOC_READ_WRITE << 30)
+        | ((len as libc::c_ulong & 0x3fff) << 16)
+        | ((b'H' as libc::c_ulong) << 8)
+        | nr as libc::c_ulong
+}
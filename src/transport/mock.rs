@@ -0,0 +1,91 @@
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+
+use crate::device::Bus;
+use crate::transport::Transport;
+use crate::{Error, Result};
+
+/// In-memory transport for running controller logic without hardware.
+///
+/// Input reports are queued with [`MockTransport::push_input`] and handed out
+/// in order; everything written is recorded for inspection.
+#[derive(Debug)]
+pub struct MockTransport {
+    bus: Bus,
+    inputs: VecDeque<Vec<u8>>,
+    outputs: Vec<Vec<u8>>,
+    features: HashMap<u8, Vec<u8>>,
+    features_written: Vec<Vec<u8>>,
+}
+
+impl MockTransport {
+    pub fn new(bus: Bus) -> Self {
+        MockTransport {
+            bus,
+            inputs: VecDeque::new(),
+            outputs: Vec::new(),
+            features: HashMap::new(),
+            features_written: Vec::new(),
+        }
+    }
+
+    /// Queues an input report to be returned by the next read.
+    pub fn push_input(&mut self, report: &[u8]) {
+        self.inputs.push_back(report.to_vec());
+    }
+
+    /// Sets the answer to a feature report request. `report` starts with the
+    /// report id.
+    pub fn set_feature_response(&mut self, report: &[u8]) {
+        if let Some(&id) = report.first() {
+            self.features.insert(id, report.to_vec());
+        }
+    }
+
+    /// Output reports written so far, oldest first.
+    pub fn outputs(&self) -> &[Vec<u8>] {
+        &self.outputs
+    }
+
+    /// Feature reports written so far, oldest first.
+    pub fn features_written(&self) -> &[Vec<u8>] {
+        &self.features_written
+    }
+
+    pub fn pending_inputs(&self) -> usize {
+        self.inputs.len()
+    }
+}
+
+impl Transport for MockTransport {
+    fn bus(&self) -> Bus {
+        self.bus
+    }
+
+    fn read_input(&mut self, buf: &mut [u8], _timeout: Duration) -> Result<usize> {
+        let report = self.inputs.pop_front().ok_or(Error::Timeout)?;
+        let len = report.len().min(buf.len());
+        buf[..len].copy_from_slice(&report[..len]);
+        Ok(len)
+    }
+
+    fn write_output(&mut self, report: &[u8]) -> Result<usize> {
+        self.outputs.push(report.to_vec());
+        Ok(report.len())
+    }
+
+    fn get_feature(&mut self, report_id: u8, buf: &mut [u8]) -> Result<usize> {
+        let report = self
+            .features
+            .get(&report_id)
+            .ok_or(Error::UnsupportedReport(report_id))?;
+        let len = report.len().min(buf.len());
+        buf[..len].copy_from_slice(&report[..len]);
+        Ok(len)
+    }
+
+    fn set_feature(&mut self, report: &[u8]) -> Result<()> {
+        self.features_written.push(report.to_vec());
+        Ok(())
+    }
+}
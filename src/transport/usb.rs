@@ -0,0 +1,308 @@
+use std::time::Duration;
+
+use rusb::{Context, Device, DeviceDescriptor, DeviceHandle, Direction, TransferType, UsbContext};
+
+use crate::device::{Bus, DUALSENSE_PRODUCT_ID, SONY_VENDOR_ID};
+use crate::transport::Transport;
+use crate::{Error, Result};
+
+const HID_GET_REPORT: u8 = 0x01;
+const HID_SET_REPORT: u8 = 0x09;
+const HID_REPORT_TYPE_FEATURE: u16 = 0x03;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Endpoint {
+    pub config: u8,
+    pub iface: u8,
+    pub setting: u8,
+    pub address: u8,
+}
+
+/// Transport over libusb with the controller's HID interface claimed.
+///
+/// The kernel driver is detached while the transport is alive and reattached
+/// when it is dropped.
+pub struct UsbTransport<T: UsbContext = Context> {
+    device: Device<T>,
+    device_desc: DeviceDescriptor,
+    handle: DeviceHandle<T>,
+    input: Endpoint,
+    output: Endpoint,
+    has_kernel_driver: bool,
+    timeout: Duration,
+}
+
+impl UsbTransport<Context> {
+    /// Opens the first DualSense found on the default libusb context.
+    pub fn open() -> Result<Self> {
+        let mut context = Context::new()?;
+        Self::open_with(&mut context, SONY_VENDOR_ID, DUALSENSE_PRODUCT_ID)
+    }
+}
+
+impl<T: UsbContext> UsbTransport<T> {
+    /// Opens the first device matching `vid`/`pid` on `context`, then claims
+    /// and configures its interrupt interface.
+    pub fn open_with(context: &mut T, vid: u16, pid: u16) -> Result<Self> {
+        let (device, device_desc, handle) = open_device(context, vid, pid)?;
+        Self::from_device(device, device_desc, handle)
+    }
+
+    /// Claims and configures the interrupt interface of an already opened
+    /// device.
+    pub fn from_device(
+        mut device: Device<T>,
+        device_desc: DeviceDescriptor,
+        mut handle: DeviceHandle<T>,
+    ) -> Result<Self> {
+        let input = find_endpoint(
+            &mut device,
+            &device_desc,
+            Direction::In,
+            TransferType::Interrupt,
+        )
+        .ok_or(Error::EndpointNotFound)?;
+        let output = find_endpoint(
+            &mut device,
+            &device_desc,
+            Direction::Out,
+            TransferType::Interrupt,
+        )
+        .ok_or(Error::EndpointNotFound)?;
+
+        let has_kernel_driver = match handle.kernel_driver_active(input.iface) {
+            Ok(true) => {
+                handle.detach_kernel_driver(input.iface)?;
+                true
+            }
+            _ => false,
+        };
+
+        if let Err(e) = configure_endpoint(&mut handle, &input) {
+            if has_kernel_driver {
+                handle.attach_kernel_driver(input.iface).ok();
+            }
+            return Err(e.into());
+        }
+
+        Ok(UsbTransport {
+            device,
+            device_desc,
+            handle,
+            input,
+            output,
+            has_kernel_driver,
+            timeout: Duration::from_secs(1),
+        })
+    }
+
+    pub fn device(&self) -> &Device<T> {
+        &self.device
+    }
+
+    pub fn device_descriptor(&self) -> &DeviceDescriptor {
+        &self.device_desc
+    }
+
+    pub fn handle(&self) -> &DeviceHandle<T> {
+        &self.handle
+    }
+
+    pub fn input_endpoint(&self) -> Endpoint {
+        self.input
+    }
+
+    pub fn output_endpoint(&self) -> Endpoint {
+        self.output
+    }
+
+    /// Whether a kernel driver was bound to the interface before it was claimed.
+    pub fn had_kernel_driver(&self) -> bool {
+        self.has_kernel_driver
+    }
+
+    /// Timeout used for output and feature report transfers.
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    pub fn manufacturer(&self) -> Result<String> {
+        let language = self.language()?;
+        Ok(self
+            .handle
+            .read_manufacturer_string(language, &self.device_desc, self.timeout)?)
+    }
+
+    pub fn product(&self) -> Result<String> {
+        let language = self.language()?;
+        Ok(self
+            .handle
+            .read_product_string(language, &self.device_desc, self.timeout)?)
+    }
+
+    pub fn serial_number(&self) -> Result<String> {
+        let language = self.language()?;
+        Ok(self
+            .handle
+            .read_serial_number_string(language, &self.device_desc, self.timeout)?)
+    }
+
+    fn language(&self) -> Result<rusb::Language> {
+        self.handle
+            .read_languages(self.timeout)?
+            .first()
+            .copied()
+            .ok_or(Error::Usb(rusb::Error::NotFound))
+    }
+}
+
+impl<T: UsbContext> Transport for UsbTransport<T> {
+    fn bus(&self) -> Bus {
+        Bus::Usb
+    }
+
+    fn read_input(&mut self, buf: &mut [u8], timeout: Duration) -> Result<usize> {
+        Ok(self
+            .handle
+            .read_interrupt(self.input.address, buf, timeout)?)
+    }
+
+    fn write_output(&mut self, report: &[u8]) -> Result<usize> {
+        Ok(self
+            .handle
+            .write_interrupt(self.output.address, report, self.timeout)?)
+    }
+
+    fn get_feature(&mut self, report_id: u8, buf: &mut [u8]) -> Result<usize> {
+        Ok(get_feature_report(
+            &self.handle,
+            self.input.iface,
+            report_id,
+            buf,
+            self.timeout,
+        )?)
+    }
+
+    fn set_feature(&mut self, report: &[u8]) -> Result<()> {
+        let report_id = *report.first().ok_or(Error::ReportTooShort {
+            expected: 1,
+            actual: 0,
+        })?;
+        let request_type = rusb::request_type(
+            Direction::Out,
+            rusb::RequestType::Class,
+            rusb::Recipient::Interface,
+        );
+        self.handle.write_control(
+            request_type,
+            HID_SET_REPORT,
+            HID_REPORT_TYPE_FEATURE << 8 | report_id as u16,
+            self.input.iface as u16,
+            report,
+            self.timeout,
+        )?;
+        Ok(())
+    }
+}
+
+impl<T: UsbContext> Drop for UsbTransport<T> {
+    fn drop(&mut self) {
+        self.handle.release_interface(self.input.iface).ok();
+        if self.has_kernel_driver {
+            self.handle.attach_kernel_driver(self.input.iface).ok();
+        }
+    }
+}
+
+/// Finds the first device matching `vid`/`pid` and opens it.
+pub fn open_device<T: UsbContext>(
+    context: &mut T,
+    vid: u16,
+    pid: u16,
+) -> Result<(Device<T>, DeviceDescriptor, DeviceHandle<T>)> {
+    for device in context.devices()?.iter() {
+        let device_desc = match device.device_descriptor() {
+            Ok(d) => d,
+            Err(_) => continue,
+        };
+
+        if device_desc.vendor_id() == vid && device_desc.product_id() == pid {
+            let handle = device.open()?;
+            return Ok((device, device_desc, handle));
+        }
+    }
+
+    Err(Error::DeviceNotFound { vid, pid })
+}
+
+/// Reads a feature report from interface `iface` with a HID GET_REPORT
+/// control transfer. The interface must be claimed.
+pub fn get_feature_report<T: UsbContext>(
+    handle: &DeviceHandle<T>,
+    iface: u8,
+    report_id: u8,
+    buf: &mut [u8],
+    timeout: Duration,
+) -> rusb::Result<usize> {
+    let request_type = rusb::request_type(
+        Direction::In,
+        rusb::RequestType::Class,
+        rusb::Recipient::Interface,
+    );
+    handle.read_control(
+        request_type,
+        HID_GET_REPORT,
+        HID_REPORT_TYPE_FEATURE << 8 | report_id as u16,
+        iface as u16,
+        buf,
+        timeout,
+    )
+}
+
+/// Finds the first endpoint with the given direction and transfer type.
+pub fn find_endpoint<T: UsbContext>(
+    device: &mut Device<T>,
+    device_desc: &DeviceDescriptor,
+    direction: Direction,
+    transfer_type: TransferType,
+) -> Option<Endpoint> {
+    for n in 0..device_desc.num_configurations() {
+        let config_desc = match device.config_descriptor(n) {
+            Ok(c) => c,
+            Err(_) => continue,
+        };
+
+        for interface in config_desc.interfaces() {
+            for interface_desc in interface.descriptors() {
+                for endpoint_desc in interface_desc.endpoint_descriptors() {
+                    if endpoint_desc.direction() == direction
+                        && endpoint_desc.transfer_type() == transfer_type
+                    {
+                        return Some(Endpoint {
+                            config: config_desc.number(),
+                            iface: interface_desc.interface_number(),
+                            setting: interface_desc.setting_number(),
+                            address: endpoint_desc.address(),
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    None
+}
+
+/// Selects the endpoint's configuration, then claims its interface and
+/// alternate setting.
+pub fn configure_endpoint<T: UsbContext>(
+    handle: &mut DeviceHandle<T>,
+    endpoint: &Endpoint,
+) -> rusb::Result<()> {
+    if handle.active_configuration()? != endpoint.config {
+        handle.set_active_configuration(endpoint.config)?;
+    }
+    handle.claim_interface(endpoint.iface)?;
+    handle.set_alternate_setting(endpoint.iface, endpoint.setting)?;
+    Ok(())
+}
@@ -0,0 +1,171 @@
+use std::time::Duration;
+
+use rust_dualsense::capture::{CaptureRecord, Direction};
+use rust_dualsense::{
+    Bus, CaptureHeader, CaptureReader, CaptureWriter, Clock, Error, FakeClock, InputState, Replay,
+    ReplaySpeed,
+};
+
+fn header() -> CaptureHeader {
+    CaptureHeader {
+        vendor_id: 0x054c,
+        product_id: 0x0ce6,
+        bus: Bus::Bluetooth,
+        serial: String::from("a0:5a:5e:12:34:56"),
+        firmware_version: 0x0110_002a,
+    }
+}
+
+fn record(ms: u64, direction: Direction, data: &[u8]) -> CaptureRecord {
+    CaptureRecord {
+        timestamp: Duration::from_millis(ms),
+        direction,
+        data: data.to_vec(),
+    }
+}
+
+fn capture(header: &CaptureHeader, records: &[CaptureRecord]) -> Vec<u8> {
+    let mut writer = CaptureWriter::new(Vec::new(), header).unwrap();
+    for record in records {
+        writer.write_record(record).unwrap();
+    }
+    writer.into_inner()
+}
+
+/// Inputs at 5, 15 and 35 ms with an output report in between.
+fn timed_capture() -> Vec<u8> {
+    let input = InputState::default().to_usb_bytes();
+    let header = CaptureHeader {
+        bus: Bus::Usb,
+        ..header()
+    };
+    capture(
+        &header,
+        &[
+            record(5, Direction::Input, &input),
+            record(10, Direction::Output, &[0x02, 0x00]),
+            record(15, Direction::Input, &input),
+            record(35, Direction::Input, &input),
+        ],
+    )
+}
+
+/// Clock readings at which each input of `timed_capture` is handed out.
+fn replay_times(speed: ReplaySpeed) -> Vec<Duration> {
+    let clock = FakeClock::new();
+    clock.set(Duration::from_secs(1));
+    let bytes = timed_capture();
+    let reader = CaptureReader::new(&bytes[..]).unwrap();
+    let mut replay = Replay::with_clock(reader, speed, clock.clone());
+
+    let mut times = Vec::new();
+    while replay.next_state().unwrap().is_some() {
+        times.push(clock.now() - Duration::from_secs(1));
+    }
+    times
+}
+
+#[test]
+fn records_round_trip() {
+    let records = [
+        record(0, Direction::Input, &[0x31; 78]),
+        record(4, Direction::Output, &[0x31, 0x10, 0x10]),
+        record(8, Direction::Input, &[]),
+    ];
+    let bytes = capture(&header(), &records);
+
+    let reader = CaptureReader::new(&bytes[..]).unwrap();
+    assert_eq!(reader.header(), &header());
+    let read: Vec<_> = reader.map(Result::unwrap).collect();
+    assert_eq!(read, records);
+}
+
+#[test]
+fn header_layout() {
+    let bytes = capture(&header(), &[]);
+    assert_eq!(&bytes[..6], b"DSCAP\x01");
+    assert_eq!(&bytes[6..10], &[0x4c, 0x05, 0xe6, 0x0c]);
+    assert_eq!(bytes[10], 1);
+    assert_eq!(&bytes[11..15], &[0x2a, 0x00, 0x10, 0x01]);
+    assert_eq!(&bytes[15..17], &[17, 0]);
+    assert_eq!(&bytes[17..], b"a0:5a:5e:12:34:56");
+}
+
+#[test]
+fn rejects_bad_magic() {
+    let mut bytes = capture(&header(), &[]);
+    bytes[0] = b'X';
+    assert!(matches!(
+        CaptureReader::new(&bytes[..]),
+        Err(Error::InvalidCapture(_))
+    ));
+}
+
+#[test]
+fn rejects_unsupported_version() {
+    let mut bytes = capture(&header(), &[]);
+    bytes[5] = 2;
+    assert!(matches!(
+        CaptureReader::new(&bytes[..]),
+        Err(Error::InvalidCapture("unsupported capture version"))
+    ));
+}
+
+#[test]
+fn rejects_what_does_not_fit_the_format() {
+    let long_serial = CaptureHeader {
+        serial: "x".repeat(0x10000),
+        ..header()
+    };
+    assert!(matches!(
+        CaptureWriter::new(Vec::new(), &long_serial),
+        Err(Error::InvalidCapture(_))
+    ));
+
+    let mut writer = CaptureWriter::new(Vec::new(), &header()).unwrap();
+    let huge = record(0, Direction::Input, &vec![0; 0x10000]);
+    assert!(matches!(
+        writer.write_record(&huge),
+        Err(Error::InvalidCapture(_))
+    ));
+    assert_eq!(writer.into_inner(), capture(&header(), &[]));
+}
+
+#[test]
+fn replay_at_max_speed_does_not_wait() {
+    assert_eq!(replay_times(ReplaySpeed::Max), [Duration::ZERO; 3]);
+}
+
+#[test]
+fn replay_at_original_speed_keeps_gaps() {
+    assert_eq!(
+        replay_times(ReplaySpeed::Original),
+        [0, 10, 30].map(Duration::from_millis)
+    );
+}
+
+#[test]
+fn replay_at_scaled_speed_divides_gaps() {
+    assert_eq!(
+        replay_times(ReplaySpeed::Scaled(2.0)),
+        [0, 5, 15].map(Duration::from_millis)
+    );
+    assert_eq!(
+        replay_times(ReplaySpeed::Scaled(0.5)),
+        [0, 20, 60].map(Duration::from_millis)
+    );
+}
+
+#[test]
+fn replay_with_invalid_rate_plays_at_max_speed() {
+    for rate in [0.0, -1.0, f64::NAN] {
+        assert_eq!(replay_times(ReplaySpeed::Scaled(rate)), [Duration::ZERO; 3]);
+    }
+}
+
+#[test]
+fn replay_with_tiny_rate_does_not_panic() {
+    let times = replay_times(ReplaySpeed::Scaled(f64::MIN_POSITIVE));
+    assert_eq!(times.len(), 3);
+    assert_eq!(times[0], Duration::ZERO);
+}
@@ -0,0 +1,26 @@
+//! Fixtures shared by the integration tests.
+
+/// `device/uevent` files of hidraw nodes, as sysfs has them.
+pub const USB_UEVENT: &str = "DRIVER=playstation
+HID_ID=0003:0000054C:00000CE6
+HID_NAME=Sony Interactive Entertainment Wireless Controller
+HID_PHYS=usb-0000:00:14.0-3/input3
+HID_UNIQ=a0:5a:5e:12:34:56
+MODALIAS=hid:b0003g0000v0000054Cp00000CE6
+";
+
+pub const BLUETOOTH_UEVENT: &str = "DRIVER=playstation
+HID_ID=0005:0000054C:00000CE6
+HID_NAME=DualSense Wireless Controller
+HID_PHYS=00:1a:7d:da:71:10
+HID_UNIQ=a0:5a:5e:65:43:21
+MODALIAS=hid:b0005g0000v0000054Cp00000CE6
+";
+
+pub const MOUSE_UEVENT: &str = "DRIVER=hid-generic
+HID_ID=0003:0000046D:0000C077
+HID_NAME=Logitech USB Optical Mouse
+HID_PHYS=usb-0000:00:14.0-2/input0
+HID_UNIQ=
+MODALIAS=hid:b0003g0001v0000046Dp0000C077
+";
@@ -0,0 +1,143 @@
+#[cfg(target_os = "linux")]
+mod common;
+
+use rust_dualsense::{ControllerModel, SONY_VENDOR_ID};
+
+#[test]
+fn models_from_usb_ids() {
+    let cases = [
+        (0x0ce6, Some(ControllerModel::DualSense)),
+        (0x0df2, Some(ControllerModel::DualSenseEdge)),
+        (0x05c4, Some(ControllerModel::DualShock4)),
+        (0x09cc, Some(ControllerModel::DualShock4)),
+        (0x0268, None),
+    ];
+    for (pid, model) in cases {
+        assert_eq!(ControllerModel::from_ids(SONY_VENDOR_ID, pid), model);
+    }
+    assert_eq!(ControllerModel::from_ids(0x045e, 0x0ce6), None);
+}
+
+#[test]
+fn every_model_maps_back_from_its_ids() {
+    for model in ControllerModel::ALL {
+        for &pid in model.product_ids() {
+            assert_eq!(ControllerModel::from_ids(SONY_VENDOR_ID, pid), Some(model));
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod hidraw {
+    use std::path::PathBuf;
+
+    use crate::common::{BLUETOOTH_UEVENT, MOUSE_UEVENT, USB_UEVENT};
+    use rust_dualsense::discovery::{fill_usb_serials, hidraw_devices};
+    use rust_dualsense::transport::hidraw::{parse_uevent, HidrawDevice};
+    use rust_dualsense::{Bus, ControllerModel, DiscoveredDevice, TransportKind};
+
+    const HUB_UEVENT: &str = "DRIVER=playstation
+HID_ID=0003:0000054C:00000DF2
+HID_NAME=Sony Interactive Entertainment DualSense Edge Wireless Controller
+HID_PHYS=usb-0000:00:14.0-3.2/input3
+HID_UNIQ=a0:5a:5e:ab:cd:ef
+";
+
+    fn nodes() -> Vec<HidrawDevice> {
+        [USB_UEVENT, HUB_UEVENT, BLUETOOTH_UEVENT, MOUSE_UEVENT]
+            .iter()
+            .enumerate()
+            .map(|(n, uevent)| {
+                let path = PathBuf::from(format!("/nonexistent/hidraw{}", n));
+                parse_uevent(uevent, path).unwrap()
+            })
+            .collect()
+    }
+
+    fn usb(product_id: u16, path: &str) -> DiscoveredDevice {
+        DiscoveredDevice {
+            model: ControllerModel::from_ids(0x054c, product_id).unwrap(),
+            vendor_id: 0x054c,
+            product_id,
+            transport: TransportKind::Usb,
+            bus: Bus::Usb,
+            serial: None,
+            path: path.to_string(),
+            openable: true,
+            instance: 0,
+        }
+    }
+
+    #[test]
+    fn lists_controllers_among_hidraw_nodes() {
+        let devices = hidraw_devices(&nodes());
+        let listed: Vec<_> = devices
+            .iter()
+            .map(|device| (device.model, device.bus, device.serial.as_deref()))
+            .collect();
+        assert_eq!(
+            listed,
+            [
+                (
+                    ControllerModel::DualSense,
+                    Bus::Usb,
+                    Some("a0:5a:5e:12:34:56")
+                ),
+                (
+                    ControllerModel::DualSenseEdge,
+                    Bus::Usb,
+                    Some("a0:5a:5e:ab:cd:ef")
+                ),
+                (
+                    ControllerModel::DualSense,
+                    Bus::Bluetooth,
+                    Some("a0:5a:5e:65:43:21")
+                ),
+            ]
+        );
+        assert!(devices
+            .iter()
+            .all(|device| device.transport == TransportKind::Hidraw && !device.openable));
+        assert_eq!(devices[0].path, "/nonexistent/hidraw0");
+    }
+
+    #[test]
+    fn usb_entries_take_the_serial_of_the_node_on_their_port() {
+        let mut devices = [
+            usb(0x0ce6, "1-3"),
+            usb(0x0df2, "1-3.2"),
+            usb(0x0ce6, "1-4"),
+            usb(0x0ce6, "1-3.2"),
+        ];
+        fill_usb_serials(&mut devices, &nodes());
+        let serials: Vec<_> = devices.iter().map(|d| d.serial.as_deref()).collect();
+        assert_eq!(
+            serials,
+            [
+                Some("a0:5a:5e:12:34:56"),
+                Some("a0:5a:5e:ab:cd:ef"),
+                // Nothing on that port, and the wrong product on the hub.
+                None,
+                None,
+            ]
+        );
+    }
+
+    #[test]
+    fn usb_serials_are_kept_or_left_empty_when_ambiguous() {
+        let mut own = usb(0x0ce6, "1-3");
+        own.serial = Some(String::from("own"));
+        let mut devices = [own, usb(0x0ce6, "2-3")];
+
+        // The same port chain on a second host controller.
+        let mut nodes = nodes();
+        let mut other = nodes[0].clone();
+        other.phys = String::from("usb-0000:03:00.0-3/input3");
+        other.uniq = String::from("a0:5a:5e:00:00:01");
+        nodes.push(other);
+
+        fill_usb_serials(&mut devices, &nodes);
+        assert_eq!(devices[0].serial.as_deref(), Some("own"));
+        assert_eq!(devices[1].serial, None);
+    }
+}
@@ -0,0 +1,186 @@
+use std::time::Duration;
+
+use rust_dualsense::{
+    Axis, Bus, Button, DualSense, Error, Event, EventConfig, EventGenerator, InputState,
+    MockTransport, PowerStatus,
+};
+
+/// State at `ms` milliseconds on the sensor clock with `buttons` held and
+/// the battery well above low.
+fn state(ms: u32, buttons: &[Button]) -> InputState {
+    let mut state = InputState {
+        sensor_timestamp: ms * 3000,
+        power: PowerStatus {
+            level: 8,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    for &button in buttons {
+        state.buttons.set(button, true);
+    }
+    state
+}
+
+fn with_left_x(mut state: InputState, x: u8) -> InputState {
+    state.left_stick.x = x;
+    state
+}
+
+#[test]
+fn reports_presses_and_releases() {
+    let mut generator = EventGenerator::default();
+    assert!(generator.update(&state(0, &[])).is_empty());
+    assert_eq!(
+        generator.update(&state(4, &[Button::Cross, Button::L1])),
+        [
+            Event::ButtonPressed(Button::Cross),
+            Event::ButtonPressed(Button::L1)
+        ]
+    );
+    assert!(generator
+        .update(&state(8, &[Button::Cross, Button::L1]))
+        .is_empty());
+    assert_eq!(
+        generator.update(&state(12, &[Button::L1, Button::Circle])),
+        [
+            Event::ButtonReleased(Button::Cross),
+            Event::ButtonPressed(Button::Circle)
+        ]
+    );
+    assert_eq!(generator.now(), Duration::from_millis(12));
+}
+
+#[test]
+fn buttons_down_in_the_first_state_are_pressed() {
+    let mut generator = EventGenerator::default();
+    assert_eq!(
+        generator.update(&state(0, &[Button::Ps])),
+        [Event::ButtonPressed(Button::Ps)]
+    );
+}
+
+#[test]
+fn held_fires_once_after_threshold() {
+    let mut generator = EventGenerator::default();
+    generator.update(&state(0, &[Button::Square]));
+    assert!(generator.update(&state(499, &[Button::Square])).is_empty());
+    assert_eq!(
+        generator.update(&state(500, &[Button::Square])),
+        [Event::ButtonHeld(
+            Button::Square,
+            Duration::from_millis(500)
+        )]
+    );
+    assert!(generator.update(&state(2000, &[Button::Square])).is_empty());
+}
+
+#[test]
+fn held_repeats_at_interval() {
+    let mut generator = EventGenerator::new(EventConfig {
+        hold_threshold: Duration::from_millis(300),
+        hold_repeat: Some(Duration::from_millis(100)),
+        ..Default::default()
+    });
+    generator.update(&state(0, &[Button::R1]));
+
+    let mut held = Vec::new();
+    for ms in (10..=520).step_by(10) {
+        for event in generator.update(&state(ms, &[Button::R1])) {
+            if let Event::ButtonHeld(_, duration) = event {
+                held.push(duration.as_millis());
+            }
+        }
+    }
+    assert_eq!(held, [300, 400, 500]);
+
+    assert_eq!(
+        generator.update(&state(530, &[])),
+        [Event::ButtonReleased(Button::R1)]
+    );
+    generator.update(&state(540, &[Button::R1]));
+    assert!(generator.update(&state(800, &[Button::R1])).is_empty());
+    assert_eq!(
+        generator.update(&state(840, &[Button::R1])),
+        [Event::ButtonHeld(Button::R1, Duration::from_millis(300))]
+    );
+}
+
+#[test]
+fn axis_changes_respect_threshold_with_hysteresis() {
+    let mut config = EventConfig::default();
+    config.set_axis_threshold(Axis::LeftX, 10);
+    let mut generator = EventGenerator::new(config);
+    let idle = state(0, &[]);
+
+    // The first state is the baseline.
+    assert!(generator.update(&with_left_x(idle, 128)).is_empty());
+    // Jitter below the threshold is ignored, in both directions.
+    for x in [133, 124, 137, 119] {
+        assert!(generator.update(&with_left_x(idle, x)).is_empty());
+    }
+    assert_eq!(
+        generator.update(&with_left_x(idle, 138)),
+        [Event::AxisChanged {
+            axis: Axis::LeftX,
+            value: 138,
+            previous: 128,
+        }]
+    );
+    // Changes are measured from the last reported value, not the last state.
+    assert!(generator.update(&with_left_x(idle, 129)).is_empty());
+    assert!(generator.update(&with_left_x(idle, 145)).is_empty());
+    assert_eq!(
+        generator.update(&with_left_x(idle, 128)),
+        [Event::AxisChanged {
+            axis: Axis::LeftX,
+            value: 128,
+            previous: 138,
+        }]
+    );
+}
+
+#[test]
+fn zero_threshold_reports_every_change() {
+    let mut config = EventConfig::default();
+    config.set_axis_threshold(Axis::R2, 0);
+    let mut generator = EventGenerator::new(config);
+    let mut idle = state(0, &[]);
+    generator.update(&idle);
+    assert!(generator.update(&idle).is_empty());
+    idle.r2 = 1;
+    assert_eq!(
+        generator.update(&idle),
+        [Event::AxisChanged {
+            axis: Axis::R2,
+            value: 1,
+            previous: 0,
+        }]
+    );
+}
+
+#[test]
+fn iterator_drains_events_in_order() {
+    let mut transport = MockTransport::new(Bus::Usb);
+    transport.push_input(&state(0, &[Button::Cross]).to_usb_bytes());
+    transport.push_input(&state(4, &[Button::Cross]).to_usb_bytes());
+    transport.push_input(&with_left_x(state(8, &[Button::Triangle]), 0).to_usb_bytes());
+    let mut dualsense = DualSense::new(transport);
+
+    let mut events = dualsense.events(EventGenerator::default());
+    let drained: Vec<_> = events.by_ref().take(4).map(Result::unwrap).collect();
+    assert_eq!(
+        drained,
+        [
+            Event::ButtonPressed(Button::Cross),
+            Event::ButtonReleased(Button::Cross),
+            Event::ButtonPressed(Button::Triangle),
+            Event::AxisChanged {
+                axis: Axis::LeftX,
+                value: 0,
+                previous: 128,
+            },
+        ]
+    );
+    assert!(matches!(events.next(), Some(Err(Error::Timeout))));
+}
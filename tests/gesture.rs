@@ -0,0 +1,236 @@
+use rust_dualsense::{
+    Gesture, GestureConfig, GestureRecognizer, InputState, SwipeDirection, TouchContact,
+};
+
+/// Report interval of the controller, in milliseconds.
+const STEP_MS: u32 = 4;
+
+/// Builds touch frames one report at a time, advancing the sensor clock.
+struct Trace {
+    recognizer: GestureRecognizer,
+    sensor_timestamp: u32,
+    gestures: Vec<Gesture>,
+}
+
+impl Trace {
+    fn new(config: GestureConfig) -> Self {
+        Trace {
+            recognizer: GestureRecognizer::new(config),
+            sensor_timestamp: 0,
+            gestures: Vec::new(),
+        }
+    }
+
+    fn frame(&mut self, touch: [Option<(u8, u16, u16)>; 2]) {
+        let state = InputState {
+            touch: touch.map(|contact| match contact {
+                Some((id, x, y)) => TouchContact {
+                    active: true,
+                    id,
+                    x,
+                    y,
+                },
+                None => TouchContact::default(),
+            }),
+            sensor_timestamp: self.sensor_timestamp,
+            ..Default::default()
+        };
+        self.gestures.extend(self.recognizer.update(&state));
+        self.sensor_timestamp = self.sensor_timestamp.wrapping_add(STEP_MS * 3000);
+    }
+
+    /// Holds one finger still for `ms`.
+    fn hold(&mut self, id: u8, x: u16, y: u16, ms: u32) {
+        for _ in 0..ms / STEP_MS {
+            self.frame([Some((id, x, y)), None]);
+        }
+    }
+
+    /// Moves one finger in a straight line over `ms`.
+    fn drag(&mut self, id: u8, from: (u16, u16), to: (u16, u16), ms: u32) {
+        let steps = ms / STEP_MS;
+        for i in 0..=steps {
+            let x = from.0 as i32 + (to.0 as i32 - from.0 as i32) * i as i32 / steps as i32;
+            let y = from.1 as i32 + (to.1 as i32 - from.1 as i32) * i as i32 / steps as i32;
+            self.frame([Some((id, x as u16, y as u16)), None]);
+        }
+    }
+
+    /// Moves two fingers in straight lines over `ms`.
+    fn drag2(&mut self, a: [(u16, u16); 2], b: [(u16, u16); 2], ms: u32) {
+        let steps = ms / STEP_MS;
+        let lerp = |from: u16, to: u16, i: u32| {
+            (from as i32 + (to as i32 - from as i32) * i as i32 / steps as i32) as u16
+        };
+        for i in 0..=steps {
+            self.frame([
+                Some((1, lerp(a[0].0, a[1].0, i), lerp(a[0].1, a[1].1, i))),
+                Some((2, lerp(b[0].0, b[1].0, i), lerp(b[0].1, b[1].1, i))),
+            ]);
+        }
+    }
+
+    /// No fingers for `ms`.
+    fn idle(&mut self, ms: u32) {
+        for _ in 0..ms / STEP_MS {
+            self.frame([None, None]);
+        }
+    }
+}
+
+fn trace() -> Trace {
+    Trace::new(GestureConfig::default())
+}
+
+#[test]
+fn tap_is_sent_after_double_tap_interval() {
+    let mut t = trace();
+    t.hold(1, 500, 400, 100);
+    t.idle(200);
+    assert!(t.gestures.is_empty());
+    t.idle(200);
+    assert_eq!(t.gestures, [Gesture::Tap { x: 500, y: 400 }]);
+}
+
+#[test]
+fn tap_without_double_tap_is_immediate() {
+    let mut t = Trace::new(GestureConfig {
+        double_tap_interval: Default::default(),
+        ..Default::default()
+    });
+    t.hold(1, 500, 400, 100);
+    t.idle(4);
+    assert_eq!(t.gestures, [Gesture::Tap { x: 500, y: 400 }]);
+}
+
+#[test]
+fn double_tap() {
+    let mut t = trace();
+    t.hold(1, 500, 400, 80);
+    t.idle(100);
+    t.hold(2, 520, 410, 80);
+    t.idle(500);
+    assert_eq!(t.gestures, [Gesture::DoubleTap { x: 520, y: 410 }]);
+}
+
+#[test]
+fn slow_second_tap_is_two_taps() {
+    let mut t = trace();
+    t.hold(1, 500, 400, 80);
+    t.idle(400);
+    t.hold(2, 500, 400, 80);
+    t.idle(400);
+    assert_eq!(
+        t.gestures,
+        [
+            Gesture::Tap { x: 500, y: 400 },
+            Gesture::Tap { x: 500, y: 400 }
+        ]
+    );
+}
+
+#[test]
+fn long_press_is_sent_while_held() {
+    let mut t = trace();
+    t.hold(1, 300, 300, 596);
+    assert!(t.gestures.is_empty());
+    t.hold(1, 300, 300, 8);
+    assert_eq!(t.gestures, [Gesture::LongPress { x: 300, y: 300 }]);
+    t.hold(1, 300, 300, 1000);
+    t.idle(500);
+    assert_eq!(t.gestures.len(), 1);
+}
+
+#[test]
+fn one_finger_swipes() {
+    let cases = [
+        ((1500, 500), (500, 520), SwipeDirection::Left),
+        ((500, 500), (1500, 480), SwipeDirection::Right),
+        ((900, 900), (920, 100), SwipeDirection::Up),
+        ((900, 100), (880, 900), SwipeDirection::Down),
+    ];
+    for (from, to, direction) in cases {
+        let mut t = trace();
+        t.drag(1, from, to, 200);
+        t.idle(500);
+        assert_eq!(
+            t.gestures,
+            [Gesture::Swipe {
+                fingers: 1,
+                direction
+            }]
+        );
+    }
+}
+
+#[test]
+fn slow_or_short_drag_is_not_a_swipe() {
+    let mut t = trace();
+    t.drag(1, (500, 500), (1500, 500), 1000);
+    t.drag(2, (500, 500), (600, 500), 100);
+    t.idle(500);
+    assert!(t.gestures.is_empty(), "{:?}", t.gestures);
+}
+
+#[test]
+fn two_finger_swipe_also_scrolls() {
+    let mut t = trace();
+    t.drag2([(400, 300), (400, 900)], [(800, 300), (800, 900)], 200);
+    t.idle(500);
+
+    assert_eq!(
+        t.gestures.last(),
+        Some(&Gesture::Swipe {
+            fingers: 2,
+            direction: SwipeDirection::Down
+        })
+    );
+    let scrolled: i32 = t
+        .gestures
+        .iter()
+        .map(|g| match g {
+            Gesture::Scroll { dx, dy } => {
+                assert_eq!(*dx, 0);
+                *dy
+            }
+            _ => 0,
+        })
+        .sum();
+    // Once scrolling starts it catches up on the distance below the
+    // threshold, so the deltas add up to the whole movement.
+    assert_eq!(scrolled, 600);
+}
+
+#[test]
+fn scroll_reports_deltas() {
+    let mut t = trace();
+    t.drag2([(400, 500), (100, 500)], [(800, 500), (500, 500)], 1000);
+    t.idle(500);
+
+    assert!(t
+        .gestures
+        .iter()
+        .all(|g| matches!(g, Gesture::Scroll { dx, dy: 0 } if *dx < 0)));
+    let scrolled: i32 = t
+        .gestures
+        .iter()
+        .map(|g| match g {
+            Gesture::Scroll { dx, .. } => *dx,
+            _ => 0,
+        })
+        .sum();
+    assert_eq!(scrolled, -300);
+}
+
+#[test]
+fn pinch_reports_scale() {
+    let mut t = trace();
+    t.drag2([(800, 500), (500, 500)], [(1000, 500), (1300, 500)], 500);
+    t.idle(500);
+
+    assert!(t
+        .gestures
+        .iter()
+        .all(|g| matches!(g, Gesture::Pinch { .. })));
+    assert_eq!(t.gestures.last(), Some(&Gesture::Pinch { scale: 4.0 }));
+}
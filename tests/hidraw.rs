@@ -0,0 +1,48 @@
+#![cfg(target_os = "linux")]
+
+mod common;
+
+use std::path::PathBuf;
+
+use common::{BLUETOOTH_UEVENT, MOUSE_UEVENT, USB_UEVENT};
+use rust_dualsense::transport::hidraw::parse_uevent;
+use rust_dualsense::Bus;
+
+#[test]
+fn parses_usb_uevent() {
+    let device = parse_uevent(USB_UEVENT, PathBuf::from("/dev/hidraw3")).unwrap();
+    assert_eq!(device.path, PathBuf::from("/dev/hidraw3"));
+    assert_eq!(device.bus, Bus::Usb);
+    assert_eq!((device.vendor_id, device.product_id), (0x054c, 0x0ce6));
+    assert_eq!(device.uniq, "a0:5a:5e:12:34:56");
+    assert_eq!(device.phys, "usb-0000:00:14.0-3/input3");
+    assert_eq!(
+        device.name,
+        "Sony Interactive Entertainment Wireless Controller"
+    );
+}
+
+#[test]
+fn parses_bluetooth_uevent() {
+    let device = parse_uevent(BLUETOOTH_UEVENT, PathBuf::from("/dev/hidraw5")).unwrap();
+    assert_eq!(device.bus, Bus::Bluetooth);
+    assert_eq!((device.vendor_id, device.product_id), (0x054c, 0x0ce6));
+    assert_eq!(device.uniq, "a0:5a:5e:65:43:21");
+    assert_eq!(device.phys, "00:1a:7d:da:71:10");
+}
+
+#[test]
+fn parses_other_vendors_with_empty_uniq() {
+    let device = parse_uevent(MOUSE_UEVENT, PathBuf::from("/dev/hidraw0")).unwrap();
+    assert_eq!(device.bus, Bus::Usb);
+    assert_eq!((device.vendor_id, device.product_id), (0x046d, 0xc077));
+    assert_eq!(device.uniq, "");
+}
+
+#[test]
+fn rejects_other_buses_and_missing_id() {
+    let i2c = USB_UEVENT.replace("HID_ID=0003", "HID_ID=0018");
+    assert!(parse_uevent(&i2c, PathBuf::from("/dev/hidraw1")).is_none());
+    let no_id = "HID_NAME=Something\nHID_UNIQ=\n";
+    assert!(parse_uevent(no_id, PathBuf::from("/dev/hidraw1")).is_none());
+}
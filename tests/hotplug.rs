@@ -0,0 +1,223 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::Duration;
+
+use rust_dualsense::{
+    Bus, ControllerModel, DiscoveredDevice, DualSense, FakeClock, HotplugEvent, HotplugMonitor,
+    ReattachingDualSense, SimulatedDualSense, Transport, TransportKind,
+};
+
+const SERIAL: &str = "a0:5a:5e:12:34:56";
+
+fn device(path: &str, transport: TransportKind, serial: Option<&str>) -> DiscoveredDevice {
+    DiscoveredDevice {
+        model: ControllerModel::DualSense,
+        vendor_id: 0x054c,
+        product_id: 0x0ce6,
+        transport,
+        bus: Bus::Usb,
+        serial: serial.map(String::from),
+        path: path.to_string(),
+        openable: true,
+        instance: 0,
+    }
+}
+
+/// A polling monitor over a device list the test can change.
+fn monitor() -> (
+    HotplugMonitor<FakeClock>,
+    Rc<RefCell<Vec<DiscoveredDevice>>>,
+) {
+    let connected = Rc::new(RefCell::new(Vec::new()));
+    let scanned = connected.clone();
+    let monitor = HotplugMonitor::polling(move || Ok(scanned.borrow().clone()), FakeClock::new());
+    (monitor, connected)
+}
+
+#[test]
+fn first_poll_reports_connected_devices() {
+    let (mut monitor, connected) = monitor();
+    let hidraw = device("/dev/hidraw3", TransportKind::Hidraw, Some(SERIAL));
+    connected.borrow_mut().push(hidraw.clone());
+
+    let events = monitor.poll(Duration::ZERO).unwrap();
+    assert_eq!(
+        events,
+        [HotplugEvent::Connected {
+            device: hidraw,
+            info: None
+        }]
+    );
+    assert_eq!(monitor.devices().len(), 1);
+}
+
+#[test]
+fn reports_changes_on_the_poll_interval() {
+    let (mut monitor, connected) = monitor();
+    monitor.set_poll_interval(Duration::from_millis(500));
+    assert!(monitor.poll(Duration::ZERO).unwrap().is_empty());
+
+    let usb = device("1-3", TransportKind::Usb, None);
+    connected.borrow_mut().push(usb.clone());
+    assert!(monitor.poll(Duration::from_millis(100)).unwrap().is_empty());
+    assert_eq!(
+        monitor.poll(Duration::from_secs(1)).unwrap(),
+        [HotplugEvent::Connected {
+            device: usb.clone(),
+            info: None
+        }]
+    );
+
+    connected.borrow_mut().clear();
+    assert_eq!(
+        monitor.poll(Duration::from_secs(1)).unwrap(),
+        [HotplugEvent::Disconnected(usb.id())]
+    );
+}
+
+#[test]
+fn replug_on_the_same_path_is_reported() {
+    let (mut monitor, connected) = monitor();
+    let first = device("/dev/hidraw3", TransportKind::Hidraw, Some(SERIAL));
+    connected.borrow_mut().push(first.clone());
+    monitor.poll(Duration::ZERO).unwrap();
+
+    let second = DiscoveredDevice {
+        instance: 1,
+        ..first.clone()
+    };
+    *connected.borrow_mut() = vec![second.clone()];
+    assert_eq!(
+        monitor.poll(Duration::from_secs(2)).unwrap(),
+        [
+            HotplugEvent::Disconnected(first.id()),
+            HotplugEvent::Connected {
+                device: second,
+                info: None
+            },
+        ]
+    );
+}
+
+#[test]
+fn info_reader_fills_in_unknown_serials() {
+    let (mut monitor, connected) = monitor();
+    monitor.set_info_reader(|_| DualSense::new(SimulatedDualSense::new(Bus::Usb)).info());
+    connected
+        .borrow_mut()
+        .push(device("1-3", TransportKind::Usb, None));
+
+    let events = monitor.poll(Duration::ZERO).unwrap();
+    let HotplugEvent::Connected { device, info } = &events[0] else {
+        panic!("expected a connected device, got {:?}", events);
+    };
+    assert_eq!(device.serial.as_deref(), Some(SERIAL));
+    assert_eq!(info.as_ref().unwrap().serial(), SERIAL);
+
+    // The serial is kept while the device stays connected.
+    assert!(monitor.poll(Duration::from_secs(2)).unwrap().is_empty());
+    assert_eq!(monitor.devices()[0].serial.as_deref(), Some(SERIAL));
+}
+
+type Opened = Rc<RefCell<Vec<String>>>;
+
+/// Reattacher whose devices open as simulated controllers with the default
+/// MAC address, recording which paths were opened.
+fn reattacher(
+    serial: &str,
+    monitor: HotplugMonitor<FakeClock>,
+) -> (ReattachingDualSense<FakeClock>, Opened) {
+    let opened = Rc::new(RefCell::new(Vec::new()));
+    let log = opened.clone();
+    let reattacher = ReattachingDualSense::with_opener(serial, monitor, move |device| {
+        log.borrow_mut().push(device.path.clone());
+        Ok(Box::new(SimulatedDualSense::new(Bus::Usb)) as Box<dyn Transport>)
+    });
+    (reattacher, opened)
+}
+
+#[test]
+fn reattaches_when_serial_comes_back() {
+    let (monitor, connected) = monitor();
+    let (mut controller, _) = reattacher(SERIAL, monitor);
+    let hidraw = device("/dev/hidraw3", TransportKind::Hidraw, Some(SERIAL));
+
+    controller.poll(Duration::ZERO).unwrap();
+    assert!(!controller.is_attached());
+
+    connected.borrow_mut().push(hidraw.clone());
+    controller.poll(Duration::from_secs(2)).unwrap();
+    assert_eq!(controller.attached_device(), Some(&hidraw.id()));
+    assert!(controller
+        .dualsense()
+        .unwrap()
+        .read_state(Duration::ZERO)
+        .is_ok());
+
+    connected.borrow_mut().clear();
+    controller.poll(Duration::from_secs(2)).unwrap();
+    assert!(!controller.is_attached());
+
+    let back = device("/dev/hidraw7", TransportKind::Hidraw, Some(SERIAL));
+    connected.borrow_mut().push(back.clone());
+    controller.poll(Duration::from_secs(2)).unwrap();
+    assert_eq!(controller.attached_device(), Some(&back.id()));
+
+    // Unplugged and plugged back in between two scans.
+    let replugged = DiscoveredDevice {
+        instance: 1,
+        ..back
+    };
+    *connected.borrow_mut() = vec![replugged.clone()];
+    controller.poll(Duration::from_secs(2)).unwrap();
+    assert_eq!(controller.attached_device(), Some(&replugged.id()));
+}
+
+#[test]
+fn prefers_hidraw_and_ignores_other_serials() {
+    let (monitor, connected) = monitor();
+    connected.borrow_mut().extend([
+        device("1-3", TransportKind::Usb, Some(SERIAL)),
+        device(
+            "/dev/hidraw1",
+            TransportKind::Hidraw,
+            Some("00:11:22:33:44:55"),
+        ),
+        device(
+            "/dev/hidraw3",
+            TransportKind::Hidraw,
+            Some(&SERIAL.to_uppercase()),
+        ),
+    ]);
+    let (mut controller, opened) = reattacher(SERIAL, monitor);
+
+    controller.poll(Duration::ZERO).unwrap();
+    assert_eq!(*opened.borrow(), ["/dev/hidraw3"]);
+    assert_eq!(controller.attached_device().unwrap().path, "/dev/hidraw3");
+}
+
+#[test]
+fn serial_from_the_info_reader_is_followed() {
+    let (mut monitor, connected) = monitor();
+    monitor.set_info_reader(|_| DualSense::new(SimulatedDualSense::new(Bus::Usb)).info());
+    connected
+        .borrow_mut()
+        .push(device("1-3", TransportKind::Usb, None));
+    let (mut controller, _) = reattacher(SERIAL, monitor);
+    controller.poll(Duration::ZERO).unwrap();
+    assert_eq!(controller.attached_device().unwrap().path, "1-3");
+}
+
+#[test]
+fn devices_of_unknown_serial_are_not_opened() {
+    let (monitor, connected) = monitor();
+    connected
+        .borrow_mut()
+        .push(device("1-3", TransportKind::Usb, None));
+    let (mut controller, opened) = reattacher(SERIAL, monitor);
+
+    controller.poll(Duration::ZERO).unwrap();
+    controller.poll(Duration::from_secs(2)).unwrap();
+    assert!(!controller.is_attached());
+    assert!(opened.borrow().is_empty());
+}
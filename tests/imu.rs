@@ -0,0 +1,107 @@
+use rust_dualsense::{
+    AxisCalibration, Bus, DualSense, Error, ImuCalibration, InputState, SimulatedDualSense,
+};
+
+/// Calibration report 0x05 as read over USB.
+#[rustfmt::skip]
+const CALIBRATION_USB: [u8; 41] = [
+    0x05, 0xfd, 0xff, 0x02, 0x00, 0x00, 0x00, 0xa6, 0x22, 0x5b, 0xdd, 0xb1, 0x22, 0x50, 0xdd, 0x9e,
+    0x22, 0x60, 0xdd, 0x1c, 0x02, 0x1c, 0x02, 0x07, 0x20, 0xff, 0xdf, 0x26, 0x20, 0x20, 0xe0, 0x03,
+    0x20, 0x07, 0xe0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+/// The same report over Bluetooth, ending with its checksum.
+#[rustfmt::skip]
+const CALIBRATION_BT: [u8; 41] = [
+    0x05, 0xfd, 0xff, 0x02, 0x00, 0x00, 0x00, 0xa6, 0x22, 0x5b, 0xdd, 0xb1, 0x22, 0x50, 0xdd, 0x9e,
+    0x22, 0x60, 0xdd, 0x1c, 0x02, 0x1c, 0x02, 0x07, 0x20, 0xff, 0xdf, 0x26, 0x20, 0x20, 0xe0, 0x03,
+    0x20, 0x07, 0xe0, 0x00, 0x00, 0x56, 0xd7, 0x8c, 0xab,
+];
+
+fn axis(bias: i32, numer: i32, denom: i32) -> AxisCalibration {
+    AxisCalibration { bias, numer, denom }
+}
+
+#[test]
+fn calibration_matches_hid_playstation() {
+    let calibration = ImuCalibration::parse(&CALIBRATION_USB, Bus::Usb).unwrap();
+    assert_eq!(
+        calibration.gyro,
+        [
+            axis(0, 1_105_920, 17739),
+            axis(0, 1_105_920, 17761),
+            axis(0, 1_105_920, 17726),
+        ]
+    );
+    assert_eq!(
+        calibration.accel,
+        [
+            axis(3, 16384, 16392),
+            axis(35, 16384, 16390),
+            axis(5, 16384, 16380),
+        ]
+    );
+}
+
+#[test]
+fn bluetooth_calibration_checks_crc() {
+    let usb = ImuCalibration::parse(&CALIBRATION_USB, Bus::Usb).unwrap();
+    assert_eq!(
+        ImuCalibration::parse(&CALIBRATION_BT, Bus::Bluetooth).unwrap(),
+        usb
+    );
+
+    let mut corrupted = CALIBRATION_BT;
+    corrupted[10] ^= 0x01;
+    assert!(matches!(
+        ImuCalibration::parse(&corrupted, Bus::Bluetooth),
+        Err(Error::CrcMismatch { .. })
+    ));
+}
+
+#[test]
+fn raw_values_are_converted_to_physical_units() {
+    let calibration = ImuCalibration::parse(&CALIBRATION_USB, Bus::Usb).unwrap();
+    let state = InputState {
+        gyro: [1000, -2000, 32767],
+        accel: [0, 8230, -8180],
+        ..Default::default()
+    };
+    let sample = calibration.apply(&state);
+    assert_eq!(sample.gyro, [60.881836, -121.61426, 1996.4092]);
+    assert_eq!(sample.accel, [-0.00024414063, 1.0, -0.9992676]);
+}
+
+#[test]
+fn zero_range_falls_back_to_nominal_scale() {
+    let mut values = [0; 17];
+    values[9] = 540;
+    values[10] = 540;
+    let calibration = ImuCalibration::from_raw(values);
+    assert_eq!(calibration.gyro[0], axis(0, 2048 * 1024, 32767));
+    assert_eq!(calibration.accel[2], axis(0, 4 * 8192, 32767));
+}
+
+#[test]
+fn imu_fields_round_trip_through_input_reports() {
+    let state = InputState {
+        gyro: [-1, 300, -32768],
+        accel: [8192, -4, 12],
+        ..Default::default()
+    };
+    for bus in [Bus::Usb, Bus::Bluetooth] {
+        let parsed = InputState::parse(&state.to_bytes(bus)).unwrap();
+        assert_eq!((parsed.gyro, parsed.accel), (state.gyro, state.accel));
+    }
+}
+
+#[test]
+fn read_motion_uses_the_controllers_calibration() {
+    let mut simulated = SimulatedDualSense::new(Bus::Bluetooth);
+    simulated.state_mut().accel = [0, 0, 8240];
+    let mut dualsense = DualSense::new(simulated);
+
+    let (_, sample) = dualsense.read_motion(std::time::Duration::ZERO).unwrap();
+    assert!((sample.accel[2] - 1.0).abs() < 0.01);
+    assert_eq!(sample.gyro, [0.0; 3]);
+}
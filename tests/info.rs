@@ -0,0 +1,75 @@
+use rust_dualsense::{
+    Bus, DualSense, Error, FirmwareInfo, MockTransport, PairingInfo, SimulatedDualSense,
+};
+
+#[test]
+fn reads_info_over_usb_and_bluetooth() {
+    for bus in [Bus::Usb, Bus::Bluetooth] {
+        let mut dualsense = DualSense::new(SimulatedDualSense::new(bus));
+        let info = dualsense.info().unwrap();
+
+        assert_eq!(info.firmware.build_date, "Jun 15 2021");
+        assert_eq!(info.firmware.build_time, "08:32:03");
+        assert_eq!(info.firmware.hardware_version, 0x0000_0614);
+        assert_eq!(info.firmware.firmware_version, 0x0110_002a);
+        assert_eq!(info.firmware.update_version, 0x0224);
+        assert_eq!(info.pairing.mac, [0xa0, 0x5a, 0x5e, 0x12, 0x34, 0x56]);
+        assert_eq!(info.pairing.host_mac, [0x00, 0x1a, 0x7d, 0xda, 0x71, 0x10]);
+        assert_eq!(info.serial(), "a0:5a:5e:12:34:56");
+    }
+}
+
+#[test]
+fn info_reads_as_text() {
+    let mut dualsense = DualSense::new(SimulatedDualSense::new(Bus::Usb));
+    let info = dualsense.info().unwrap();
+    assert_eq!(
+        info.to_string(),
+        "MAC address: a0:5a:5e:12:34:56\n\
+         paired host: 00:1a:7d:da:71:10\n\
+         firmware built: Jun 15 2021 08:32:03\n\
+         hardware version: 0x00000614\n\
+         firmware version: 0x0110002a\n\
+         update version: 0x0224"
+    );
+}
+
+#[test]
+fn pairing_addresses_are_reversed() {
+    let mut report = [0; 20];
+    report[0] = 0x09;
+    report[1..7].copy_from_slice(&[6, 5, 4, 3, 2, 1]);
+    report[10..16].copy_from_slice(&[0x16, 0x15, 0x14, 0x13, 0x12, 0x11]);
+
+    let info = PairingInfo::parse(&report, Bus::Usb).unwrap();
+    assert_eq!(info.mac, [1, 2, 3, 4, 5, 6]);
+    assert_eq!(info.host_mac, [0x11, 0x12, 0x13, 0x14, 0x15, 0x16]);
+}
+
+#[test]
+fn rejects_bad_feature_reports() {
+    let mut report = [0; 64];
+    report[0] = 0x20;
+    assert!(matches!(
+        FirmwareInfo::parse(&report[..40], Bus::Usb),
+        Err(Error::ReportTooShort {
+            expected: 64,
+            actual: 40
+        })
+    ));
+    assert!(matches!(
+        FirmwareInfo::parse(&report, Bus::Bluetooth),
+        Err(Error::CrcMismatch { .. })
+    ));
+    report[0] = 0x21;
+    assert!(matches!(
+        FirmwareInfo::parse(&report, Bus::Usb),
+        Err(Error::UnexpectedReportId(0x21))
+    ));
+}
+
+#[test]
+fn missing_feature_report_is_an_error() {
+    let mut dualsense = DualSense::new(MockTransport::new(Bus::Usb));
+    assert!(dualsense.info().is_err());
+}
@@ -0,0 +1,155 @@
+use rust_dualsense::{Bus, Button, Buttons, DPad, Error, InputParser, InputState, Stick};
+
+/// Sticks at (0x12, 0xe4) and (0x80, 0x7f), R2 fully pulled, sequence 42,
+/// d-pad down-left with square, circle, L1, L2, options, R3, touchpad and
+/// mute held, sensor clock at 0x12345678, no touch.
+#[rustfmt::skip]
+const SAMPLE_USB: [u8; 64] = [
+    0x01, 0x12, 0xe4, 0x80, 0x7f, 0x00, 0xff, 0x2a, 0x55, 0xa5, 0x06, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x78, 0x56, 0x34, 0x12,
+    0x00, 0x80, 0x00, 0x00, 0x00, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+/// The same input as `SAMPLE_USB` in a Bluetooth report 0x31 with sequence
+/// tag 0x01 and its checksum.
+#[rustfmt::skip]
+const SAMPLE_BT: [u8; 78] = [
+    0x31, 0x01, 0x12, 0xe4, 0x80, 0x7f, 0x00, 0xff, 0x2a, 0x55, 0xa5, 0x06, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x78, 0x56, 0x34,
+    0x12, 0x00, 0x80, 0x00, 0x00, 0x00, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x8f, 0x9b, 0x3d, 0x2a,
+];
+
+/// Report 0x01 with only the three button bytes set.
+fn buttons_report(hat_and_face: u8, shoulder: u8, system: u8) -> [u8; 64] {
+    let mut report = [0; 64];
+    report[0] = 0x01;
+    report[8] = hat_and_face;
+    report[9] = shoulder;
+    report[10] = system;
+    report
+}
+
+#[test]
+fn parses_golden_usb_report() {
+    let state = InputState::parse(&SAMPLE_USB).unwrap();
+    assert_eq!(state.left_stick, Stick { x: 0x12, y: 0xe4 });
+    assert_eq!(state.right_stick, Stick { x: 0x80, y: 0x7f });
+    assert_eq!(state.l2, 0x00);
+    assert_eq!(state.r2, 0xff);
+    assert_eq!(state.sequence, 42);
+    assert_eq!(state.sensor_timestamp, 0x1234_5678);
+    assert_eq!(state.timestamp_us(), 0x1234_5678 / 3);
+    assert_eq!(state.dpad, DPad::DownLeft);
+    assert!(state.touch.iter().all(|contact| !contact.active));
+
+    let mut expected = Buttons::default();
+    for button in [
+        Button::Square,
+        Button::Circle,
+        Button::L1,
+        Button::L2,
+        Button::Options,
+        Button::R3,
+        Button::Touchpad,
+        Button::Mute,
+        Button::DPadDown,
+        Button::DPadLeft,
+    ] {
+        expected.set(button, true);
+    }
+    assert_eq!(state.buttons, expected);
+}
+
+#[test]
+fn every_button_bit() {
+    let cases = [
+        (Button::Square, buttons_report(0x18, 0, 0)),
+        (Button::Cross, buttons_report(0x28, 0, 0)),
+        (Button::Circle, buttons_report(0x48, 0, 0)),
+        (Button::Triangle, buttons_report(0x88, 0, 0)),
+        (Button::L1, buttons_report(0x08, 0x01, 0)),
+        (Button::R1, buttons_report(0x08, 0x02, 0)),
+        (Button::L2, buttons_report(0x08, 0x04, 0)),
+        (Button::R2, buttons_report(0x08, 0x08, 0)),
+        (Button::Create, buttons_report(0x08, 0x10, 0)),
+        (Button::Options, buttons_report(0x08, 0x20, 0)),
+        (Button::L3, buttons_report(0x08, 0x40, 0)),
+        (Button::R3, buttons_report(0x08, 0x80, 0)),
+        (Button::Ps, buttons_report(0x08, 0, 0x01)),
+        (Button::Touchpad, buttons_report(0x08, 0, 0x02)),
+        (Button::Mute, buttons_report(0x08, 0, 0x04)),
+    ];
+    for (button, report) in cases {
+        let state = InputState::parse(&report).unwrap();
+        assert_eq!(state.buttons.iter().collect::<Vec<_>>(), [button]);
+        assert_eq!(state.dpad, DPad::Neutral);
+    }
+}
+
+#[test]
+fn every_dpad_hat_value() {
+    let cases = [
+        (0, DPad::Up, &[Button::DPadUp][..]),
+        (1, DPad::UpRight, &[Button::DPadUp, Button::DPadRight]),
+        (2, DPad::Right, &[Button::DPadRight]),
+        (3, DPad::DownRight, &[Button::DPadRight, Button::DPadDown]),
+        (4, DPad::Down, &[Button::DPadDown]),
+        (5, DPad::DownLeft, &[Button::DPadDown, Button::DPadLeft]),
+        (6, DPad::Left, &[Button::DPadLeft]),
+        (7, DPad::UpLeft, &[Button::DPadUp, Button::DPadLeft]),
+        (8, DPad::Neutral, &[]),
+    ];
+    for (hat, dpad, pressed) in cases {
+        let state = InputState::parse(&buttons_report(hat, 0, 0)).unwrap();
+        assert_eq!(state.dpad, dpad, "hat {}", hat);
+        assert_eq!(state.buttons.iter().collect::<Vec<_>>(), pressed);
+    }
+}
+
+#[test]
+fn usb_report_round_trips() {
+    let state = InputState::parse(&SAMPLE_USB).unwrap();
+    assert_eq!(state.to_usb_bytes(), SAMPLE_USB);
+}
+
+#[test]
+fn parses_golden_bluetooth_report() {
+    let (state, bus) = InputState::parse_with_bus(&SAMPLE_BT).unwrap();
+    assert_eq!(bus, Bus::Bluetooth);
+    assert_eq!(state, InputState::parse(&SAMPLE_USB).unwrap());
+    assert_eq!(state.to_bluetooth_bytes()[2..74], SAMPLE_BT[2..74]);
+}
+
+#[test]
+fn rejects_corrupted_bluetooth_report() {
+    let mut report = SAMPLE_BT;
+    report[3] ^= 0x01;
+    assert!(matches!(
+        InputState::parse(&report),
+        Err(Error::CrcMismatch {
+            expected: 0x2a3d_9b8f,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn parser_counts_dropped_reports() {
+    let mut corrupted = SAMPLE_BT;
+    corrupted[77] ^= 0xff;
+    let mut parser = InputParser::new();
+
+    assert!(parser.parse(&SAMPLE_BT).is_ok());
+    assert_eq!(parser.dropped(), 0);
+    assert!(matches!(
+        parser.parse(&corrupted),
+        Err(Error::CrcMismatch { .. })
+    ));
+    assert_eq!(parser.dropped(), 1);
+    assert!(parser.parse(&SAMPLE_USB).is_ok());
+    assert!(parser.parse(&SAMPLE_BT).is_ok());
+    assert_eq!(parser.dropped(), 1);
+}
@@ -0,0 +1,117 @@
+use std::time::Duration;
+
+use rust_dualsense::{
+    Animation, Bus, Clock, Color, DualSense, Easing, FakeClock, Keyframe, LightbarAnimator,
+    OutputReport, SimulatedDualSense,
+};
+
+const RED: Color = Color::new(0xff, 0x00, 0x00);
+const BLUE: Color = Color::new(0x00, 0x00, 0xff);
+
+fn ms(n: u64) -> Duration {
+    Duration::from_millis(n)
+}
+
+#[test]
+fn keyframes_interpolate_with_easing() {
+    let animation = Animation::Keyframes {
+        frames: vec![
+            Keyframe::new(ms(0), RED, Easing::Linear),
+            Keyframe::new(ms(100), BLUE, Easing::Linear),
+            Keyframe::new(ms(200), RED, Easing::Step),
+        ],
+        looped: false,
+    };
+    assert_eq!(animation.color_at(ms(0)), RED);
+    assert_eq!(animation.color_at(ms(50)), Color::new(0x80, 0x00, 0x80));
+    assert_eq!(animation.color_at(ms(150)), BLUE);
+    assert_eq!(animation.color_at(ms(200)), RED);
+    assert_eq!(animation.color_at(ms(5000)), RED);
+}
+
+#[test]
+fn looped_keyframes_restart() {
+    let animation = Animation::Keyframes {
+        frames: vec![
+            Keyframe::new(ms(0), RED, Easing::Linear),
+            Keyframe::new(ms(100), BLUE, Easing::EaseIn),
+        ],
+        looped: true,
+    };
+    assert_eq!(animation.color_at(ms(150)), animation.color_at(ms(50)));
+    assert_eq!(animation.color_at(ms(50)), Color::new(0xbf, 0x00, 0x40));
+}
+
+#[test]
+fn periodic_animations() {
+    let breathe = Animation::Breathe {
+        color: RED,
+        period: ms(1000),
+    };
+    assert_eq!(breathe.color_at(ms(0)), Color::BLACK);
+    assert_eq!(breathe.color_at(ms(500)), RED);
+    assert_eq!(breathe.color_at(ms(1500)), RED);
+
+    let strobe = Animation::Strobe {
+        color: BLUE,
+        period: ms(100),
+        duty: 0.25,
+    };
+    assert_eq!(strobe.color_at(ms(10)), BLUE);
+    assert_eq!(strobe.color_at(ms(30)), Color::BLACK);
+
+    let rainbow = Animation::Rainbow { period: ms(300) };
+    assert_eq!(rainbow.color_at(ms(0)), RED);
+    assert_eq!(rainbow.color_at(ms(100)), Color::new(0x00, 0xff, 0x00));
+    assert_eq!(rainbow.color_at(ms(200)), BLUE);
+}
+
+#[test]
+fn animator_ticks_on_the_fake_clock() {
+    let clock = FakeClock::new();
+    let mut animator = LightbarAnimator::with_clock(
+        Animation::Strobe {
+            color: RED,
+            period: ms(40),
+            duty: 0.5,
+        },
+        clock.clone(),
+    );
+    animator.set_tick(ms(10));
+    let mut report = OutputReport::new();
+
+    assert_eq!(animator.update(&mut report), Some(RED));
+    assert_eq!(animator.update(&mut report), None);
+    clock.advance(ms(9));
+    assert_eq!(animator.update(&mut report), None);
+    clock.advance(ms(1));
+    assert_eq!(animator.update(&mut report), Some(RED));
+    clock.advance(ms(15));
+    assert_eq!(animator.update(&mut report), Some(Color::BLACK));
+    assert_eq!(animator.until_next_tick(), ms(5));
+}
+
+#[test]
+fn animator_drives_the_controller() {
+    let clock = FakeClock::new();
+    let mut animator = LightbarAnimator::with_clock(
+        Animation::Keyframes {
+            frames: vec![
+                Keyframe::new(ms(0), RED, Easing::Linear),
+                Keyframe::new(ms(100), BLUE, Easing::Linear),
+            ],
+            looped: false,
+        },
+        clock.clone(),
+    );
+    animator.set_tick(ms(20));
+    let mut dualsense = DualSense::new(SimulatedDualSense::new(Bus::Bluetooth));
+    let mut report = OutputReport::new();
+
+    animator.run(&mut dualsense, &mut report, ms(200)).unwrap();
+
+    let simulated = dualsense.transport();
+    assert_eq!(simulated.outputs().len(), 10);
+    assert_eq!(simulated.output_state().lightbar, [0x00, 0x00, 0xff]);
+    assert_eq!(clock.now(), ms(200));
+}
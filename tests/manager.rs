@@ -0,0 +1,295 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::Duration;
+
+use rust_dualsense::{
+    Bus, ControllerManager, ControllerModel, DiscoveredDevice, DualSense, Error, FakeClock,
+    HotplugMonitor, MockTransport, OutputReport, PlayerLeds, Result, SimulatedDualSense, SlotEvent,
+    Transport, TransportKind, MAX_PLAYERS,
+};
+
+const SERIALS: [&str; 5] = [
+    "a0:5a:5e:00:00:01",
+    "a0:5a:5e:00:00:02",
+    "a0:5a:5e:00:00:03",
+    "a0:5a:5e:00:00:04",
+    "a0:5a:5e:00:00:05",
+];
+
+fn manager() -> (ControllerManager<SimulatedDualSense, FakeClock>, FakeClock) {
+    let clock = FakeClock::new();
+    (ControllerManager::with_clock(clock.clone()), clock)
+}
+
+fn controller() -> DualSense<SimulatedDualSense> {
+    DualSense::new(SimulatedDualSense::new(Bus::Usb))
+}
+
+fn player_leds(manager: &mut ControllerManager<SimulatedDualSense, FakeClock>, player: u8) -> u8 {
+    let dualsense = manager.dualsense(player).unwrap();
+    dualsense.transport().output_state().player_leds
+}
+
+#[test]
+fn assigns_slots_in_order_and_lights_player_leds() {
+    let (mut manager, _) = manager();
+    for (i, serial) in SERIALS[..MAX_PLAYERS].iter().enumerate() {
+        let player = i as u8 + 1;
+        assert_eq!(manager.connect(serial, controller()), Some(player));
+        assert_eq!(
+            player_leds(&mut manager, player),
+            PlayerLeds::player(player).unwrap().bits()
+        );
+    }
+    assert_eq!(manager.connect(SERIALS[4], controller()), None);
+    assert_eq!(manager.slots().len(), MAX_PLAYERS);
+}
+
+#[test]
+fn assigning_ends_the_connection_animation() {
+    let (mut manager, _) = manager();
+    manager.connect(SERIALS[0], controller());
+    let dualsense = manager.dualsense(1).unwrap();
+    assert_eq!(dualsense.transport().output_state().lightbar_setup, 2);
+}
+
+#[test]
+fn disconnected_slot_is_reserved_for_its_serial() {
+    let (mut manager, clock) = manager();
+    manager.connect(SERIALS[0], controller());
+    manager.connect(SERIALS[1], controller());
+
+    assert_eq!(
+        manager.disconnect(1),
+        Some(SlotEvent::Disconnected { player: 1 })
+    );
+    assert_eq!(manager.connect(SERIALS[2], controller()), Some(3));
+
+    clock.advance(Duration::from_secs(10));
+    assert!(manager.poll(Duration::ZERO).unwrap().is_empty());
+    let slot = manager.slot(1).unwrap();
+    assert!(!slot.connected);
+    assert_eq!(slot.disconnected_for, Some(Duration::from_secs(10)));
+
+    assert_eq!(
+        manager.connect(&SERIALS[0].to_uppercase(), controller()),
+        Some(1)
+    );
+    assert!(manager.slot(1).unwrap().connected);
+    assert_eq!(
+        player_leds(&mut manager, 1),
+        PlayerLeds::player(1).unwrap().bits()
+    );
+}
+
+#[test]
+fn slot_is_released_after_grace_period() {
+    let (mut manager, clock) = manager();
+    manager.set_grace_period(Duration::from_secs(5));
+    manager.connect(SERIALS[0], controller());
+    manager.disconnect(1);
+
+    clock.advance(Duration::from_secs(5));
+    assert_eq!(
+        manager.poll(Duration::ZERO).unwrap(),
+        [SlotEvent::Released {
+            player: 1,
+            serial: SERIALS[0].to_string(),
+        }]
+    );
+    assert!(manager.slot(1).is_none());
+    assert_eq!(manager.connect(SERIALS[1], controller()), Some(1));
+}
+
+#[test]
+fn clock_going_backwards_keeps_the_slot() {
+    let (mut manager, clock) = manager();
+    clock.set(Duration::from_secs(10));
+    manager.connect(SERIALS[0], controller());
+    manager.disconnect(1);
+
+    clock.set(Duration::from_secs(5));
+    assert!(manager.poll(Duration::ZERO).unwrap().is_empty());
+    assert_eq!(
+        manager.slot(1).unwrap().disconnected_for,
+        Some(Duration::ZERO)
+    );
+}
+
+/// Transport that never has input and records how long it was asked to
+/// wait. A real libusb transport would block forever on a zero timeout.
+struct Idle(Rc<RefCell<Vec<Duration>>>);
+
+impl Transport for Idle {
+    fn bus(&self) -> Bus {
+        Bus::Usb
+    }
+
+    fn read_input(&mut self, _buf: &mut [u8], timeout: Duration) -> Result<usize> {
+        self.0.borrow_mut().push(timeout);
+        Err(Error::Timeout)
+    }
+
+    fn write_output(&mut self, report: &[u8]) -> Result<usize> {
+        Ok(report.len())
+    }
+
+    fn get_feature(&mut self, report_id: u8, _buf: &mut [u8]) -> Result<usize> {
+        Err(Error::UnsupportedReport(report_id))
+    }
+
+    fn set_feature(&mut self, _report: &[u8]) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn poll_waits_on_the_first_controller_only() {
+    let waits = Rc::new(RefCell::new(Vec::new()));
+    let mut manager = ControllerManager::with_clock(FakeClock::new());
+    for serial in &SERIALS[..3] {
+        manager.connect(serial, DualSense::new(Idle(waits.clone())));
+    }
+
+    let ms = Duration::from_millis;
+    manager.poll(ms(10)).unwrap();
+    assert_eq!(*waits.borrow(), [ms(10), ms(1), ms(1)]);
+
+    waits.borrow_mut().clear();
+    manager.poll(Duration::ZERO).unwrap();
+    assert_eq!(*waits.borrow(), [ms(1), ms(1), ms(1)]);
+}
+
+#[test]
+fn poll_updates_slot_state() {
+    let (mut manager, _) = manager();
+    let mut dualsense = controller();
+    dualsense.transport_mut().state_mut().l2 = 200;
+    manager.connect(SERIALS[0], dualsense);
+    assert_eq!(manager.slot(1).unwrap().state, None);
+
+    manager.poll(Duration::ZERO).unwrap();
+    assert_eq!(manager.slot(1).unwrap().state.unwrap().l2, 200);
+
+    manager.disconnect(1);
+    let slot = manager.slot(1).unwrap();
+    assert_eq!(slot.serial, SERIALS[0]);
+    assert_eq!(slot.state.unwrap().l2, 200);
+}
+
+#[test]
+fn send_keeps_player_leds() {
+    let (mut manager, _) = manager();
+    manager.connect(SERIALS[0], controller());
+    manager.connect(SERIALS[1], controller());
+
+    manager
+        .send(2, OutputReport::new().lightbar(255, 0, 0).player(4))
+        .unwrap();
+    let output = *manager.dualsense(2).unwrap().transport().output_state();
+    assert_eq!(output.player_leds, PlayerLeds::player(2).unwrap().bits());
+    assert!(matches!(
+        manager.send(3, &OutputReport::new()),
+        Err(Error::NoController { player: 3 })
+    ));
+}
+
+fn device(path: &str, transport: TransportKind, serial: &str) -> DiscoveredDevice {
+    DiscoveredDevice {
+        model: ControllerModel::DualSense,
+        vendor_id: 0x054c,
+        product_id: 0x0ce6,
+        transport,
+        bus: Bus::Usb,
+        serial: Some(serial.to_string()),
+        path: path.to_string(),
+        openable: true,
+        instance: 0,
+    }
+}
+
+#[test]
+fn hotplug_connects_and_reconnects_controllers() {
+    let clock = FakeClock::new();
+    let connected = Rc::new(RefCell::new(Vec::new()));
+    let scanned = connected.clone();
+    let monitor = HotplugMonitor::polling(move || Ok(scanned.borrow().clone()), clock.clone());
+    let opened = Rc::new(RefCell::new(Vec::new()));
+    let log = opened.clone();
+
+    let mut manager = ControllerManager::with_clock(clock.clone());
+    manager.set_transport(TransportKind::Hidraw);
+    manager.watch_with(monitor, move |device| {
+        log.borrow_mut().push(device.path.clone());
+        Ok(SimulatedDualSense::new(Bus::Usb))
+    });
+
+    connected.borrow_mut().extend([
+        device("1-3", TransportKind::Usb, SERIALS[0]),
+        device("/dev/hidraw3", TransportKind::Hidraw, SERIALS[0]),
+    ]);
+    assert_eq!(
+        manager.poll(Duration::ZERO).unwrap(),
+        [SlotEvent::Assigned {
+            player: 1,
+            serial: SERIALS[0].to_string(),
+        }]
+    );
+    assert_eq!(*opened.borrow(), ["/dev/hidraw3"]);
+    assert!(manager.slot(1).unwrap().state.is_some());
+
+    connected.borrow_mut().clear();
+    clock.advance(Duration::from_secs(1));
+    assert_eq!(
+        manager.poll(Duration::ZERO).unwrap(),
+        [SlotEvent::Disconnected { player: 1 }]
+    );
+
+    connected
+        .borrow_mut()
+        .push(device("/dev/hidraw5", TransportKind::Hidraw, SERIALS[0]));
+    clock.advance(Duration::from_secs(1));
+    assert_eq!(
+        manager.poll(Duration::ZERO).unwrap(),
+        [SlotEvent::Reconnected { player: 1 }]
+    );
+}
+
+#[test]
+fn device_that_fails_to_identify_is_retried() {
+    let clock = FakeClock::new();
+    let connected = Rc::new(RefCell::new(Vec::new()));
+    let scanned = connected.clone();
+    let monitor = HotplugMonitor::polling(move || Ok(scanned.borrow().clone()), clock.clone());
+    let attempts = Rc::new(RefCell::new(0));
+    let count = attempts.clone();
+
+    let mut manager = ControllerManager::with_clock(clock.clone());
+    manager.set_transport(TransportKind::Hidraw);
+    manager.watch_with(monitor, move |_| {
+        *count.borrow_mut() += 1;
+        // The first time the pairing report is not answered.
+        let transport: Box<dyn Transport> = match *count.borrow() {
+            1 => Box::new(MockTransport::new(Bus::Usb)),
+            _ => Box::new(SimulatedDualSense::new(Bus::Usb)),
+        };
+        Ok(transport)
+    });
+
+    let mut unknown = device("/dev/hidraw3", TransportKind::Hidraw, SERIALS[0]);
+    unknown.serial = None;
+    connected.borrow_mut().push(unknown);
+    assert!(manager.poll(Duration::ZERO).unwrap().is_empty());
+    assert!(manager.poll(Duration::ZERO).unwrap().is_empty());
+    assert_eq!(*attempts.borrow(), 1);
+
+    clock.advance(Duration::from_secs(1));
+    assert_eq!(
+        manager.poll(Duration::ZERO).unwrap(),
+        [SlotEvent::Assigned {
+            player: 1,
+            serial: String::from("a0:5a:5e:12:34:56"),
+        }]
+    );
+    assert_eq!(*attempts.borrow(), 2);
+}
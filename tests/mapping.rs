@@ -0,0 +1,194 @@
+use std::fs;
+
+use rust_dualsense::{
+    Button, ButtonMapping, ControllerMapping, Error, InputState, MappedValue, Stick,
+};
+
+const TWO_FIELDS_TOML: &str = r#"
+name = "test"
+
+[[buttons]]
+name = "left_stick_x"
+bit_offset = 8
+bits = 8
+
+[[buttons]]
+name = "cross"
+bit_offset = 68
+bits = 4
+bit = 1
+"#;
+
+const TWO_FIELDS_JSON: &str = r#"{
+    "name": "test",
+    "buttons": [
+        { "name": "left_stick_x", "bit_offset": 8, "bits": 8 },
+        { "name": "cross", "bit_offset": 68, "bits": 4, "bit": 1 }
+    ]
+}"#;
+
+fn field(bit_offset: u32, bits: u32) -> ButtonMapping {
+    ButtonMapping {
+        name: String::from("field"),
+        bit_offset,
+        bits,
+        bit: None,
+        values: None,
+    }
+}
+
+fn sample_state() -> InputState {
+    let mut state = InputState {
+        left_stick: Stick { x: 0x12, y: 0x34 },
+        r2: 0xc0,
+        ..Default::default()
+    };
+    for button in [Button::Cross, Button::DPadUp, Button::DPadRight, Button::R3] {
+        state.buttons.set(button, true);
+    }
+    state
+}
+
+#[test]
+fn builtin_mapping_matches_the_parser() {
+    let mapping = ControllerMapping::dualsense();
+    assert_eq!(mapping.name, "dualsense");
+
+    let state = sample_state();
+    let mapped = mapping.decode(&state.to_usb_bytes());
+    assert_eq!(mapped.values.len(), mapping.buttons.len());
+    for button in Button::ALL {
+        // "L2" and "R2" are the analog triggers, their buttons have their
+        // own entries.
+        let name = match button {
+            Button::L2 => "L2_button",
+            Button::R2 => "R2_button",
+            _ => button.name(),
+        };
+        assert_eq!(
+            mapped.is_pressed(name),
+            state.is_pressed(button),
+            "{}",
+            name
+        );
+    }
+    assert_eq!(mapped.value("left_stick_x"), Some(0x12));
+    assert_eq!(mapped.value("left_stick_y"), Some(0x34));
+    assert_eq!(mapped.value("R2"), Some(0xc0));
+}
+
+#[test]
+fn bluetooth_reports_shift_by_one_byte() {
+    let mapping = ControllerMapping::dualsense();
+    let state = sample_state();
+    assert_eq!(
+        mapping.decode(&state.to_bluetooth_bytes()),
+        mapping.decode(&state.to_usb_bytes())
+    );
+}
+
+#[test]
+fn toml_and_json_load_the_same_mapping() {
+    let toml = ControllerMapping::from_toml_str(TWO_FIELDS_TOML).unwrap();
+    let json = ControllerMapping::from_json_str(TWO_FIELDS_JSON).unwrap();
+    assert_eq!(toml, json);
+    assert_eq!(toml.buttons.len(), 2);
+    assert_eq!(toml.buttons[1].bit, Some(1));
+}
+
+#[test]
+fn load_picks_format_from_extension() {
+    let dir = std::env::temp_dir().join(format!("dualsense-mapping-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let toml = dir.join("test.toml");
+    let json = dir.join("test.json");
+    let other = dir.join("test.yaml");
+    fs::write(&toml, TWO_FIELDS_TOML).unwrap();
+    fs::write(&json, TWO_FIELDS_JSON).unwrap();
+    fs::write(&other, TWO_FIELDS_TOML).unwrap();
+
+    assert_eq!(
+        ControllerMapping::load(&toml).unwrap(),
+        ControllerMapping::load(&json).unwrap()
+    );
+    assert!(matches!(
+        ControllerMapping::load(&other),
+        Err(Error::InvalidMapping(_))
+    ));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn rejects_unknown_fields() {
+    let typo = TWO_FIELDS_TOML.replace("bit = 1", "bti = 1");
+    assert!(matches!(
+        ControllerMapping::from_toml_str(&typo),
+        Err(Error::InvalidMapping(_))
+    ));
+    let extra = TWO_FIELDS_JSON.replace("\"name\": \"test\"", "\"name\": \"test\", \"x\": 1");
+    assert!(matches!(
+        ControllerMapping::from_json_str(&extra),
+        Err(Error::InvalidMapping(_))
+    ));
+}
+
+#[test]
+fn rejects_bits_outside_the_field() {
+    let out_of_range = TWO_FIELDS_TOML.replace("bit = 1", "bit = 4");
+    match ControllerMapping::from_toml_str(&out_of_range) {
+        Err(Error::InvalidMapping(reason)) => assert!(reason.starts_with("cross:")),
+        other => panic!("expected an invalid mapping, got {:?}", other),
+    }
+    for bits in ["bits = 0", "bits = 33"] {
+        let width = TWO_FIELDS_TOML.replace("bits = 8", bits);
+        assert!(matches!(
+            ControllerMapping::from_toml_str(&width),
+            Err(Error::InvalidMapping(_))
+        ));
+    }
+}
+
+#[test]
+fn rejects_fields_past_the_end_of_the_report() {
+    let last_byte = TWO_FIELDS_TOML.replace("bit_offset = 8", "bit_offset = 504");
+    assert!(ControllerMapping::from_toml_str(&last_byte).is_ok());
+    for offset in ["bit_offset = 505", "bit_offset = 4294967295"] {
+        let past_end = TWO_FIELDS_TOML.replace("bit_offset = 8", offset);
+        match ControllerMapping::from_toml_str(&past_end) {
+            Err(Error::InvalidMapping(reason)) => assert!(reason.starts_with("left_stick_x:")),
+            other => panic!("expected an invalid mapping, got {:?}", other),
+        }
+    }
+}
+
+#[test]
+fn fields_span_byte_boundaries() {
+    let report = [0x01, 0xab, 0xcd, 0xef];
+    // Bits 12 to 19: high nibble of 0xab, low nibble of 0xcd.
+    assert_eq!(field(12, 8).decode(&report), Some(MappedValue::Value(0xda)));
+    assert_eq!(
+        field(8, 24).decode(&report),
+        Some(MappedValue::Value(0xefcdab))
+    );
+    assert_eq!(
+        field(0, 32).decode(&report),
+        Some(MappedValue::Value(0xefcd_ab01))
+    );
+    assert_eq!(field(28, 8).decode(&report), None);
+    // Bluetooth reports shift every field by a byte.
+    assert_eq!(field(u32::MAX - 4, 8).decode(&[0x31, 0, 0]), None);
+}
+
+#[test]
+fn bit_and_values_decode_to_pressed() {
+    let report = [0x01, 0b0110_0101];
+    let mut bit = field(8, 4);
+    bit.bit = Some(2);
+    assert_eq!(bit.decode(&report), Some(MappedValue::Pressed(true)));
+    bit.bit = Some(1);
+    assert_eq!(bit.decode(&report), Some(MappedValue::Pressed(false)));
+
+    let mut values = field(12, 4);
+    values.values = Some(vec![5, 6]);
+    assert_eq!(values.decode(&report), Some(MappedValue::Pressed(true)));
+}
@@ -0,0 +1,209 @@
+use rust_dualsense::{EulerAngles, MotionSample, OrientationFilter, Quaternion};
+
+/// Report interval of the controller, in microseconds.
+const STEP_US: u32 = 4000;
+
+/// Accelerometer reading of a controller lying flat; the sensors' y axis
+/// points up out of the face and z towards the player.
+const FLAT: [f32; 3] = [0.0, 1.0, 0.0];
+
+/// Gyro reading for turning left at `rate` degrees per second.
+fn yaw(rate: f32) -> [f32; 3] {
+    [0.0, rate, 0.0]
+}
+
+/// Gyro reading for tilting to the right at `rate` degrees per second.
+fn roll(rate: f32) -> [f32; 3] {
+    [0.0, 0.0, -rate]
+}
+
+/// Feeds `seconds` of samples with constant gyro and accel readings,
+/// continuing from `*timestamp_us`.
+fn feed(
+    filter: &mut OrientationFilter,
+    timestamp_us: &mut u32,
+    seconds: f32,
+    gyro: [f32; 3],
+    accel: [f32; 3],
+) {
+    let steps = (seconds * 1_000_000.0 / STEP_US as f32).round() as u32;
+    for _ in 0..steps {
+        *timestamp_us = timestamp_us.wrapping_add(STEP_US);
+        filter.update(&MotionSample {
+            gyro,
+            accel,
+            timestamp_us: *timestamp_us,
+        });
+    }
+}
+
+fn filters() -> [OrientationFilter; 2] {
+    [
+        OrientationFilter::madgwick(0.1),
+        OrientationFilter::mahony(1.0, 0.0),
+    ]
+}
+
+fn assert_close(actual: f32, expected: f32, tolerance: f32) {
+    assert!(
+        (actual - expected).abs() <= tolerance,
+        "{} is not within {} of {}",
+        actual,
+        tolerance,
+        expected
+    );
+}
+
+#[test]
+fn flat_and_still_stays_level() {
+    for mut filter in filters() {
+        let mut t = 0;
+        feed(&mut filter, &mut t, 2.0, [0.0; 3], FLAT);
+        assert!(filter.orientation().angle() < 0.01);
+    }
+}
+
+#[test]
+fn first_sample_aligns_with_gravity() {
+    let angle = 30f32.to_radians();
+    let mut filter = OrientationFilter::default();
+    filter.update(&MotionSample {
+        gyro: [0.0; 3],
+        accel: [-angle.sin(), angle.cos(), 0.0],
+        timestamp_us: 0,
+    });
+    assert_close(filter.euler().roll, 30.0, 0.01);
+    assert_close(filter.euler().pitch, 0.0, 0.01);
+}
+
+#[test]
+fn yaw_rate_integrates_to_heading() {
+    for mut filter in filters() {
+        let mut t = 0;
+        feed(&mut filter, &mut t, 0.004, [0.0; 3], FLAT);
+        feed(&mut filter, &mut t, 1.0, yaw(90.0), FLAT);
+        let angles = filter.euler();
+        assert_close(angles.yaw, 90.0, 0.5);
+        assert_close(angles.roll, 0.0, 0.5);
+        assert_close(angles.pitch, 0.0, 0.5);
+    }
+}
+
+#[test]
+fn tilting_follows_gyro_and_agrees_with_gravity() {
+    for mut filter in filters() {
+        let mut t = 0;
+        feed(&mut filter, &mut t, 0.004, [0.0; 3], FLAT);
+        // Tilt to 45 degrees over half a second, with the accelerometer
+        // seeing gravity rotate along.
+        let mut angle: f32 = 0.0;
+        for _ in 0..125 {
+            angle += 90.0 * STEP_US as f32 / 1_000_000.0;
+            t += STEP_US;
+            filter.update(&MotionSample {
+                gyro: roll(90.0),
+                accel: [-angle.to_radians().sin(), angle.to_radians().cos(), 0.0],
+                timestamp_us: t,
+            });
+        }
+        assert_close(filter.euler().roll, 45.0, 0.5);
+        assert_close(filter.euler().pitch, 0.0, 0.5);
+    }
+}
+
+#[test]
+fn accelerometer_cancels_gyro_drift() {
+    for mut filter in filters() {
+        let mut t = 0;
+        // A 2 deg/s bias would integrate to 60 degrees in 30 seconds.
+        feed(&mut filter, &mut t, 30.0, roll(2.0), FLAT);
+        assert!(filter.euler().roll.abs() < 5.0, "{:?}", filter.euler());
+    }
+}
+
+#[test]
+fn mahony_integral_learns_gyro_bias() {
+    let mut filter = OrientationFilter::mahony(1.0, 0.1);
+    let mut t = 0;
+    feed(&mut filter, &mut t, 60.0, roll(2.0), FLAT);
+    assert!(filter.euler().roll.abs() < 0.1, "{:?}", filter.euler());
+}
+
+#[test]
+fn higher_gain_corrects_faster() {
+    let mut slow = OrientationFilter::madgwick(0.1);
+    let mut fast = OrientationFilter::madgwick(0.1);
+    fast.set_gain(1.0);
+    for filter in [&mut slow, &mut fast] {
+        let mut t = 0;
+        feed(filter, &mut t, 0.004, [0.0; 3], FLAT);
+        // The gyro reports a quick tilt the accelerometer never sees.
+        feed(filter, &mut t, 0.2, roll(100.0), FLAT);
+        feed(filter, &mut t, 1.0, [0.0; 3], FLAT);
+    }
+    assert!(fast.euler().roll.abs() < slow.euler().roll.abs());
+}
+
+#[test]
+fn reset_to_neutral_measures_from_current_pose() {
+    for mut filter in filters() {
+        let mut t = 0;
+        feed(&mut filter, &mut t, 0.004, [0.0; 3], FLAT);
+        feed(&mut filter, &mut t, 1.0, yaw(90.0), FLAT);
+        filter.reset_to_neutral();
+        assert!(filter.orientation().angle() < 0.01);
+
+        feed(&mut filter, &mut t, 0.5, yaw(-90.0), FLAT);
+        assert_close(filter.euler().yaw, -45.0, 0.5);
+        assert_close(filter.absolute_orientation().to_euler().yaw, 45.0, 0.5);
+
+        filter.reset();
+        assert_eq!(filter.orientation(), Quaternion::IDENTITY);
+    }
+}
+
+#[test]
+fn sensor_clock_wrap_is_a_normal_step() {
+    // The sensor clock counts 1/3 µs in 32 bits.
+    let wrap = ((u32::MAX as u64 + 1) / 3) as u32;
+    let mut filter = OrientationFilter::madgwick(0.0);
+    filter.update(&MotionSample {
+        gyro: [0.0; 3],
+        accel: FLAT,
+        timestamp_us: wrap - STEP_US,
+    });
+    let mut t = 0;
+    filter.update(&MotionSample {
+        gyro: yaw(90.0),
+        accel: FLAT,
+        timestamp_us: t,
+    });
+    feed(&mut filter, &mut t, 0.996, yaw(90.0), FLAT);
+    assert_close(filter.euler().yaw, 90.0, 0.5);
+}
+
+#[test]
+fn long_gaps_are_not_integrated() {
+    let mut filter = OrientationFilter::default();
+    let mut t = 0;
+    feed(&mut filter, &mut t, 0.004, [0.0; 3], FLAT);
+    filter.update(&MotionSample {
+        gyro: yaw(90.0),
+        accel: FLAT,
+        timestamp_us: 1_000_000,
+    });
+    assert!(filter.orientation().angle() < 0.01);
+}
+
+#[test]
+fn euler_round_trips_through_quaternion() {
+    let angles = EulerAngles {
+        roll: 20.0,
+        pitch: -35.0,
+        yaw: 120.0,
+    };
+    let back = Quaternion::from_euler(angles).to_euler();
+    assert_close(back.roll, angles.roll, 0.01);
+    assert_close(back.pitch, angles.pitch, 0.01);
+    assert_close(back.yaw, angles.yaw, 0.01);
+}
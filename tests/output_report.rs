@@ -0,0 +1,254 @@
+use rust_dualsense::{
+    Bus, Error, LedBrightness, MuteLed, OutputReport, PlayerLeds, Trigger, TriggerEffect,
+};
+
+fn sample_report() -> OutputReport {
+    let mut report = OutputReport::new();
+    report
+        .rumble(0x40, 0x80)
+        .lightbar(0xff, 0x00, 0x7f)
+        .player_leds(0b00100);
+    report
+}
+
+#[rustfmt::skip]
+const SAMPLE_USB: [u8; 48] = [
+    0x02, 0x03, 0x14, 0x80, 0x40, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x02, 0x00, 0x04, 0xff, 0x00, 0x7f,
+];
+
+#[rustfmt::skip]
+const SAMPLE_BT_SEQ_3: [u8; 78] = [
+    0x31, 0x30, 0x10, 0x03, 0x14, 0x80, 0x40, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x02, 0x00, 0x04, 0xff,
+    0x00, 0x7f, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xf1, 0xa6, 0x9b, 0x47,
+];
+
+/// Rumble, lightbar, player LEDs, mute LED and a right trigger effect, each
+/// with its valid flag raised, plus the LED setup the player LEDs need.
+#[rustfmt::skip]
+const FULL_USB: [u8; 48] = [
+    0x02, 0x07, 0x15, 0x80, 0x40, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x01, 0x40, 0xa0, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x02, 0x00, 0x0a, 0x10, 0x20, 0x30,
+];
+
+#[test]
+fn empty_usb_report_changes_nothing() {
+    let bytes = OutputReport::new().to_usb_bytes();
+    assert_eq!(bytes[0], 0x02);
+    assert!(bytes[1..].iter().all(|&b| b == 0));
+}
+
+#[test]
+fn usb_report_sets_every_field_and_flag() {
+    let mut report = OutputReport::new();
+    report
+        .rumble(0x40, 0x80)
+        .lightbar(0x10, 0x20, 0x30)
+        .player_leds(0b01010)
+        .mute_led(true)
+        .trigger_effect(
+            Trigger::Right,
+            TriggerEffect::ContinuousResistance {
+                start: 0x40,
+                force: 0xa0,
+            },
+        )
+        .unwrap();
+    assert_eq!(report.to_usb_bytes(), FULL_USB);
+}
+
+#[test]
+fn usb_report_matches_golden_bytes() {
+    assert_eq!(sample_report().to_usb_bytes(), SAMPLE_USB);
+}
+
+#[test]
+fn bluetooth_report_matches_golden_bytes() {
+    assert_eq!(sample_report().to_bluetooth_bytes(3), SAMPLE_BT_SEQ_3);
+}
+
+#[test]
+fn empty_bluetooth_report_has_header_and_crc() {
+    let bytes = OutputReport::new().to_bluetooth_bytes(0);
+    assert_eq!(&bytes[..3], &[0x31, 0x00, 0x10]);
+    assert!(bytes[3..74].iter().all(|&b| b == 0));
+    assert_eq!(&bytes[74..], &[0xb5, 0x01, 0x15, 0x23]);
+}
+
+#[test]
+fn bluetooth_sequence_wraps_to_four_bits() {
+    let report = OutputReport::new();
+    assert_eq!(report.to_bluetooth_bytes(0x1f)[1], 0xf0);
+    assert_eq!(
+        report.to_bluetooth_bytes(0x10),
+        report.to_bluetooth_bytes(0)
+    );
+}
+
+#[test]
+fn to_bytes_selects_layout_by_bus() {
+    let report = sample_report();
+    assert_eq!(report.to_bytes(Bus::Usb, 3), SAMPLE_USB.to_vec());
+    assert_eq!(report.to_bytes(Bus::Bluetooth, 3), SAMPLE_BT_SEQ_3.to_vec());
+}
+
+#[test]
+fn feedback_fills_zones_from_position() {
+    let effect = TriggerEffect::Feedback {
+        position: 3,
+        strength: 5,
+    };
+    assert_eq!(
+        effect.encode().unwrap(),
+        [0x21, 0xf8, 0x03, 0x00, 0x48, 0x92, 0x24, 0x00, 0x00, 0x00, 0x00]
+    );
+}
+
+#[test]
+fn slope_feedback_interpolates_strengths() {
+    let effect = TriggerEffect::SlopeFeedback {
+        start: 2,
+        end: 6,
+        start_strength: 1,
+        end_strength: 8,
+    };
+    let mut strengths = [0; 10];
+    strengths[2..].copy_from_slice(&[1, 3, 5, 6, 8, 8, 8, 8]);
+    assert_eq!(
+        effect.encode().unwrap(),
+        TriggerEffect::MultiplePositionFeedback { strengths }
+            .encode()
+            .unwrap()
+    );
+    assert_eq!(
+        effect.encode().unwrap(),
+        [0x21, 0xfc, 0x03, 0x00, 0xc4, 0xfe, 0x3f, 0x00, 0x00, 0x00, 0x00]
+    );
+}
+
+#[test]
+fn weapon_and_vibration_encode_their_modes() {
+    let weapon = TriggerEffect::Weapon {
+        start: 2,
+        end: 7,
+        strength: 6,
+    };
+    assert_eq!(
+        weapon.encode().unwrap(),
+        [0x25, 0x84, 0x00, 0x05, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]
+    );
+
+    let vibration = TriggerEffect::Vibration {
+        position: 4,
+        amplitude: 8,
+        frequency: 40,
+    };
+    assert_eq!(
+        vibration.encode().unwrap(),
+        [0x26, 0xf0, 0x03, 0x00, 0xf0, 0xff, 0x3f, 0x00, 0x00, 0x28, 0x00]
+    );
+}
+
+#[test]
+fn zero_strength_turns_the_effect_off() {
+    let off = TriggerEffect::Off.encode().unwrap();
+    assert_eq!(off[0], 0x05);
+    let effect = TriggerEffect::Vibration {
+        position: 0,
+        amplitude: 0,
+        frequency: 30,
+    };
+    assert_eq!(effect.encode().unwrap(), off);
+}
+
+#[test]
+fn out_of_range_effect_is_rejected() {
+    let mut report = OutputReport::new();
+    let result = report.trigger_effect(
+        Trigger::Right,
+        TriggerEffect::Feedback {
+            position: 10,
+            strength: 4,
+        },
+    );
+    assert!(matches!(result, Err(Error::InvalidTriggerEffect(_))));
+    assert_eq!(report.to_usb_bytes(), OutputReport::new().to_usb_bytes());
+
+    let weapon = TriggerEffect::Weapon {
+        start: 5,
+        end: 5,
+        strength: 8,
+    };
+    assert!(weapon.validate().is_err());
+}
+
+#[test]
+fn trigger_effects_land_in_their_byte_ranges() {
+    let mut report = OutputReport::new();
+    report
+        .trigger_effect(
+            Trigger::Right,
+            TriggerEffect::ContinuousResistance {
+                start: 0x40,
+                force: 0xa0,
+            },
+        )
+        .unwrap()
+        .trigger_effect(
+            Trigger::Left,
+            TriggerEffect::SectionResistance {
+                start: 0x20,
+                end: 0x80,
+                force: 0xff,
+            },
+        )
+        .unwrap();
+    let bytes = report.to_usb_bytes();
+    assert_eq!(bytes[1], 0x0c);
+    assert_eq!(&bytes[11..14], &[0x01, 0x40, 0xa0]);
+    assert_eq!(&bytes[22..26], &[0x02, 0x20, 0x80, 0xff]);
+}
+
+#[test]
+fn led_settings_raise_their_flags() {
+    let mut report = OutputReport::new();
+    report
+        .player(3)
+        .led_brightness(LedBrightness::Low)
+        .mute_led(MuteLed::Breathing)
+        .release_leds();
+    let bytes = report.to_usb_bytes();
+    assert_eq!(bytes[2], 0x11);
+    assert_eq!(bytes[9], 0x02);
+    assert_eq!(bytes[39], 0x03);
+    assert_eq!(&bytes[42..45], &[0x02, 0x02, 0b10101]);
+}
+
+#[test]
+fn player_leds_end_the_connection_animation() {
+    let bytes = OutputReport::new().player(2).to_usb_bytes();
+    assert_eq!(bytes[2], 0x10);
+    assert_eq!(bytes[39], 0x02);
+    assert_eq!(&bytes[42..45], &[0x02, 0x00, 0b01010]);
+    assert_eq!(
+        bytes,
+        OutputReport::new().release_leds().player(2).to_usb_bytes()
+    );
+}
+
+#[test]
+fn player_patterns_and_bits() {
+    assert_eq!(PlayerLeds::player(1).unwrap().bits(), 0b00100);
+    assert_eq!(PlayerLeds::player(4).unwrap().bits(), 0b11011);
+    assert_eq!(PlayerLeds::player(5), None);
+
+    let mut leds = PlayerLeds::OFF;
+    leds.set(0, true).set(4, true).set(7, true);
+    assert_eq!(leds, PlayerLeds::from_bits(0xf1));
+    assert!(leds.is_on(4) && !leds.is_on(2));
+}
@@ -0,0 +1,89 @@
+use std::time::Duration;
+
+use rust_dualsense::{
+    Bus, Clock, DualSense, FakeClock, OutputReport, RumblePattern, RumbleScheduler, RumbleSegment,
+    SimulatedDualSense,
+};
+
+fn ms(n: u64) -> Duration {
+    Duration::from_millis(n)
+}
+
+#[test]
+fn envelope_ramps_in_and_out() {
+    let segment = RumbleSegment::new(ms(100), 200, 100).with_envelope(ms(20), ms(40));
+    assert_eq!(segment.strength_at(ms(0)), (0, 0));
+    assert_eq!(segment.strength_at(ms(10)), (100, 50));
+    assert_eq!(segment.strength_at(ms(50)), (200, 100));
+    assert_eq!(segment.strength_at(ms(80)), (100, 50));
+}
+
+#[test]
+fn pattern_plays_segments_in_order() {
+    let pattern = RumblePattern::repeat(RumbleSegment::new(ms(30), 255, 0), ms(20), 2);
+    assert_eq!(pattern.duration(), ms(80));
+    assert_eq!(pattern.strength_at(ms(10)), Some((255, 0)));
+    assert_eq!(pattern.strength_at(ms(40)), Some((0, 0)));
+    assert_eq!(pattern.strength_at(ms(60)), Some((255, 0)));
+    assert_eq!(pattern.strength_at(ms(80)), None);
+}
+
+#[test]
+fn queued_patterns_follow_each_other_and_end_at_zero() {
+    let clock = FakeClock::new();
+    let mut scheduler = RumbleScheduler::with_clock(clock.clone());
+    scheduler.set_tick(ms(10));
+    let mut report = OutputReport::new();
+
+    scheduler.queue(RumblePattern::new().then(RumbleSegment::new(ms(25), 10, 20)));
+    scheduler.queue(RumblePattern::new().then(RumbleSegment::new(ms(20), 30, 40)));
+
+    let mut seen = Vec::new();
+    while scheduler.is_active() {
+        seen.extend(scheduler.update(&mut report));
+        clock.advance(ms(10));
+    }
+    assert_eq!(
+        seen,
+        [(10, 20), (10, 20), (10, 20), (30, 40), (30, 40), (0, 0)]
+    );
+    assert_eq!(scheduler.update(&mut report), None);
+}
+
+#[test]
+fn play_interrupts_and_stop_turns_motors_off() {
+    let clock = FakeClock::new();
+    let mut scheduler = RumbleScheduler::with_clock(clock.clone());
+    let mut report = OutputReport::new();
+
+    scheduler.play(RumblePattern::new().then(RumbleSegment::new(ms(1000), 100, 100)));
+    scheduler.queue(RumblePattern::new().then(RumbleSegment::new(ms(1000), 1, 1)));
+    assert_eq!(scheduler.update(&mut report), Some((100, 100)));
+
+    clock.advance(ms(5));
+    scheduler.play(RumblePattern::new().then(RumbleSegment::new(ms(1000), 50, 0)));
+    assert_eq!(scheduler.update(&mut report), Some((50, 0)));
+
+    scheduler.stop();
+    assert_eq!(scheduler.update(&mut report), Some((0, 0)));
+    assert!(!scheduler.is_active());
+}
+
+#[test]
+fn run_leaves_the_controller_still() {
+    let clock = FakeClock::new();
+    let mut scheduler = RumbleScheduler::with_clock(clock.clone());
+    let mut dualsense = DualSense::new(SimulatedDualSense::new(Bus::Usb));
+    let mut report = OutputReport::new();
+
+    scheduler.play(
+        RumblePattern::new()
+            .then(RumbleSegment::new(ms(200), 255, 128).with_envelope(ms(50), ms(50))),
+    );
+    scheduler.run(&mut dualsense, &mut report).unwrap();
+
+    let state = dualsense.transport().output_state();
+    assert_eq!((state.motor_left, state.motor_right), (0, 0));
+    assert!(dualsense.transport().outputs().len() > 10);
+    assert!(clock.now() >= ms(200));
+}
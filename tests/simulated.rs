@@ -0,0 +1,108 @@
+use std::time::Duration;
+
+use rust_dualsense::{
+    Bus, Button, DualSense, InputState, MuteLed, OutputReport, SimulatedDualSense,
+};
+
+#[test]
+fn produces_valid_usb_reports() {
+    let mut simulated = SimulatedDualSense::new(Bus::Usb);
+    let report = simulated.next_report();
+    assert_eq!(report.len(), 64);
+    assert_eq!(report[0], 0x01);
+
+    let (state, bus) = InputState::parse_with_bus(&report).unwrap();
+    assert_eq!(bus, Bus::Usb);
+    assert_eq!(state.sequence, 1);
+    assert_eq!(state.sensor_timestamp, 4000 * 3);
+}
+
+#[test]
+fn produces_crc_correct_bluetooth_reports() {
+    let mut dualsense = DualSense::new(SimulatedDualSense::new(Bus::Bluetooth));
+    for _ in 0..10 {
+        let mut buf = [0; 78];
+        assert_eq!(dualsense.read_input(&mut buf, Duration::ZERO).unwrap(), 78);
+        assert_eq!(buf[0], 0x31);
+        let (_, bus) = InputState::parse_with_bus(&buf).unwrap();
+        assert_eq!(bus, Bus::Bluetooth);
+    }
+    dualsense.read_state(Duration::ZERO).unwrap();
+    assert_eq!(dualsense.dropped_reports(), 0);
+}
+
+#[test]
+fn reflects_state_changes() {
+    let mut dualsense = DualSense::new(SimulatedDualSense::new(Bus::Usb));
+    let simulated = dualsense.transport_mut();
+    simulated.state_mut().buttons.set(Button::Triangle, true);
+    simulated.state_mut().left_stick.x = 0xff;
+    simulated.set_report_interval(Duration::from_millis(1));
+
+    let first = dualsense.read_state(Duration::ZERO).unwrap();
+    assert!(first.is_pressed(Button::Triangle));
+    assert_eq!(first.left_stick.x, 0xff);
+
+    dualsense.transport_mut().state_mut().r2 = 0x80;
+    let second = dualsense.read_state(Duration::ZERO).unwrap();
+    assert_eq!(second.r2, 0x80);
+    assert_eq!(second.sequence, first.sequence.wrapping_add(1));
+    assert_eq!(second.sensor_timestamp - first.sensor_timestamp, 3000);
+}
+
+#[test]
+fn plays_queued_states_once() {
+    let mut simulated = SimulatedDualSense::new(Bus::Usb);
+    let mut pressed = InputState::default();
+    pressed.buttons.set(Button::Cross, true);
+    simulated.queue_state(pressed);
+    simulated.queue_state(InputState::default());
+    assert_eq!(simulated.queued_states(), 2);
+
+    let mut dualsense = DualSense::new(simulated);
+    let states: Vec<_> = (0..3)
+        .map(|_| dualsense.read_state(Duration::ZERO).unwrap())
+        .collect();
+    assert!(states[0].is_pressed(Button::Cross));
+    assert!(!states[1].is_pressed(Button::Cross));
+    assert!(!states[2].is_pressed(Button::Cross));
+    assert_eq!(dualsense.transport().queued_states(), 0);
+}
+
+#[test]
+fn decodes_output_reports_on_both_buses() {
+    for bus in [Bus::Usb, Bus::Bluetooth] {
+        let mut dualsense = DualSense::new(SimulatedDualSense::new(bus));
+        let mut report = OutputReport::new();
+        report
+            .rumble(0x30, 0x60)
+            .lightbar(0x01, 0x02, 0x03)
+            .player_leds(0b10001)
+            .mute_led(MuteLed::Breathing)
+            .mic_mute(true);
+        dualsense.send(&report).unwrap();
+        dualsense
+            .send(OutputReport::new().lightbar(0, 0xff, 0))
+            .unwrap();
+
+        let simulated = dualsense.transport();
+        assert_eq!(simulated.outputs().len(), 2);
+        let output = simulated.output_state();
+        assert_eq!((output.motor_left, output.motor_right), (0x30, 0x60));
+        assert_eq!(output.lightbar, [0, 0xff, 0]);
+        assert_eq!(output.player_leds, 0b10001);
+        assert_eq!(output.mute_led, 2);
+        assert!(output.mic_muted);
+    }
+}
+
+#[test]
+fn answers_feature_reports_on_both_buses() {
+    for bus in [Bus::Usb, Bus::Bluetooth] {
+        let mut dualsense = DualSense::new(SimulatedDualSense::new(bus));
+        let info = dualsense.info().unwrap();
+        assert_eq!(info.serial(), "a0:5a:5e:12:34:56");
+        assert_eq!(info.firmware.build_date, "Jun 15 2021");
+        assert!(dualsense.imu_calibration().is_ok());
+    }
+}
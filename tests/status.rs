@@ -0,0 +1,146 @@
+use rust_dualsense::{
+    ChargingState, Event, EventGenerator, InputState, PeripheralStatus, PowerStatus,
+};
+
+fn power(level: u8, charging: ChargingState) -> PowerStatus {
+    PowerStatus { level, charging }
+}
+
+fn state(power: PowerStatus, peripherals: PeripheralStatus, timestamp_ms: u32) -> InputState {
+    InputState {
+        power,
+        peripherals,
+        sensor_timestamp: timestamp_ms * 3000,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn parses_status_bytes() {
+    let mut report = InputState::default().to_usb_bytes();
+    report[53] = 0x17;
+    report[54] = 0x05;
+
+    let state = InputState::parse(&report).unwrap();
+    assert_eq!(state.power, power(7, ChargingState::Charging));
+    assert_eq!(state.power.percentage(), 75);
+    assert_eq!(
+        state.peripherals,
+        PeripheralStatus {
+            headphones: true,
+            microphone: false,
+            mic_muted: true,
+        }
+    );
+    assert_eq!(state.to_usb_bytes(), report);
+}
+
+#[test]
+fn charging_states() {
+    let cases = [
+        (0x0, ChargingState::Discharging),
+        (0x1, ChargingState::Charging),
+        (0x2, ChargingState::Full),
+        (0xa, ChargingState::AbnormalVoltage),
+        (0xb, ChargingState::AbnormalTemperature),
+        (0xf, ChargingState::ChargingError),
+        (0x5, ChargingState::Unknown(5)),
+    ];
+    for (nibble, charging) in cases {
+        assert_eq!(ChargingState::from_nibble(nibble), charging);
+        assert_eq!(charging.to_nibble(), nibble);
+        assert_eq!(charging.is_error(), nibble > 2);
+    }
+}
+
+#[test]
+fn percentage_follows_the_kernel() {
+    assert_eq!(power(0, ChargingState::Discharging).percentage(), 5);
+    assert_eq!(power(10, ChargingState::Discharging).percentage(), 100);
+    assert_eq!(power(3, ChargingState::Full).percentage(), 100);
+    assert_eq!(power(8, ChargingState::AbnormalTemperature).percentage(), 0);
+}
+
+#[test]
+fn status_round_trips_over_bluetooth() {
+    let state = state(
+        power(4, ChargingState::ChargingError),
+        PeripheralStatus {
+            headphones: true,
+            microphone: true,
+            mic_muted: false,
+        },
+        0,
+    );
+    let parsed = InputState::parse(&state.to_bluetooth_bytes()).unwrap();
+    assert_eq!(parsed.power, state.power);
+    assert_eq!(parsed.peripherals, state.peripherals);
+}
+
+#[test]
+fn status_changes_are_events() {
+    let mut generator = EventGenerator::default();
+    let unplugged = PeripheralStatus::default();
+    let headset = PeripheralStatus {
+        headphones: true,
+        microphone: true,
+        mic_muted: false,
+    };
+
+    let full = power(9, ChargingState::Discharging);
+    assert!(generator.update(&state(full, unplugged, 0)).is_empty());
+
+    let lower = power(8, ChargingState::Discharging);
+    assert_eq!(
+        generator.update(&state(lower, headset, 4)),
+        [
+            Event::PowerChanged {
+                status: lower,
+                previous: full,
+            },
+            Event::PeripheralsChanged {
+                status: headset,
+                previous: unplugged,
+            },
+        ]
+    );
+    assert!(generator.update(&state(lower, headset, 8)).is_empty());
+}
+
+#[test]
+fn low_battery_is_sent_once_per_discharge() {
+    let mut generator = EventGenerator::default();
+    generator.config_mut().low_battery = 20;
+    let peripherals = PeripheralStatus::default();
+    let low_battery = |events: Vec<Event>| -> Vec<u8> {
+        events
+            .into_iter()
+            .filter_map(|e| match e {
+                Event::BatteryLow(percentage) => Some(percentage),
+                _ => None,
+            })
+            .collect()
+    };
+
+    let mut t = 0;
+    let mut update = |power: PowerStatus| {
+        t += 4;
+        low_battery(generator.update(&state(power, peripherals, t)))
+    };
+    assert!(update(power(2, ChargingState::Discharging)).is_empty());
+    assert_eq!(update(power(1, ChargingState::Discharging)), [15]);
+    assert!(update(power(0, ChargingState::Discharging)).is_empty());
+    assert!(update(power(0, ChargingState::Charging)).is_empty());
+    assert_eq!(update(power(0, ChargingState::Discharging)), [5]);
+}
+
+#[test]
+fn already_low_battery_is_reported_on_first_state() {
+    let mut generator = EventGenerator::default();
+    let events = generator.update(&state(
+        power(0, ChargingState::Discharging),
+        PeripheralStatus::default(),
+        0,
+    ));
+    assert_eq!(events, [Event::BatteryLow(5)]);
+}
@@ -0,0 +1,208 @@
+use rust_dualsense::{Deadzone, ResponseCurve, Stick, StickConfig, StickPosition};
+
+const EPSILON: f32 = 1e-4;
+
+fn config(deadzone: Deadzone) -> StickConfig {
+    StickConfig {
+        deadzone,
+        outer_deadzone: 1.0,
+        ..StickConfig::raw()
+    }
+}
+
+/// Raw position and the expected output.
+type Case = ((u8, u8), (f32, f32));
+
+/// Checks `config` against a table of cases.
+fn check(config: StickConfig, cases: &[Case]) {
+    for &((x, y), (expected_x, expected_y)) in cases {
+        let StickPosition { x: px, y: py } = config.process(Stick { x, y });
+        assert!(
+            (px - expected_x).abs() < EPSILON && (py - expected_y).abs() < EPSILON,
+            "({}, {}) gave ({}, {}), expected ({}, {})",
+            x,
+            y,
+            px,
+            py,
+            expected_x,
+            expected_y
+        );
+    }
+}
+
+// Raw 140 is 0.0945 of full deflection and 141 is 0.1024, on either side of
+// a 0.1 deadzone; 137 is 0.0709, which on both axes is just outside it
+// radially.
+
+#[test]
+fn raw_config_only_normalizes() {
+    check(
+        StickConfig::raw(),
+        &[
+            ((128, 128), (0.0, 0.0)),
+            ((255, 128), (1.0, 0.0)),
+            ((0, 128), (-1.0, 0.0)),
+            ((128, 0), (0.0, -1.0)),
+            ((192, 64), (0.50394, -0.5)),
+        ],
+    );
+}
+
+#[test]
+fn axial_deadzone() {
+    check(
+        config(Deadzone::Axial(0.1)),
+        &[
+            ((128, 128), (0.0, 0.0)),
+            ((140, 128), (0.0, 0.0)),
+            ((141, 128), (0.002625, 0.0)),
+            ((137, 137), (0.0, 0.0)),
+            ((140, 200), (0.0, 0.51881)),
+            ((255, 128), (1.0, 0.0)),
+            ((0, 255), (-1.0, 1.0)),
+        ],
+    );
+}
+
+#[test]
+fn radial_deadzone() {
+    check(
+        config(Deadzone::Radial(0.1)),
+        &[
+            ((128, 128), (0.0, 0.0)),
+            ((140, 128), (0.0, 0.0)),
+            ((141, 128), (0.10236, 0.0)),
+            ((137, 137), (0.070866, 0.070866)),
+            ((255, 128), (1.0, 0.0)),
+            ((128, 0), (0.0, -1.0)),
+        ],
+    );
+}
+
+#[test]
+fn scaled_radial_deadzone() {
+    check(
+        config(Deadzone::ScaledRadial(0.1)),
+        &[
+            ((128, 128), (0.0, 0.0)),
+            ((140, 128), (0.0, 0.0)),
+            ((141, 128), (0.002625, 0.0)),
+            ((192, 128), (0.44882, 0.0)),
+            ((255, 128), (1.0, 0.0)),
+            ((0, 128), (-1.0, 0.0)),
+        ],
+    );
+}
+
+#[test]
+fn deadzone_sizes_are_clamped() {
+    for size in [-0.1, -1.0] {
+        for deadzone in [
+            Deadzone::Axial(size),
+            Deadzone::Radial(size),
+            Deadzone::ScaledRadial(size),
+        ] {
+            check(
+                config(deadzone),
+                &[
+                    ((128, 128), (0.0, 0.0)),
+                    ((141, 128), (0.10236, 0.0)),
+                    ((255, 128), (1.0, 0.0)),
+                ],
+            );
+        }
+    }
+    for deadzone in [
+        Deadzone::Axial(1.5),
+        Deadzone::Radial(1.5),
+        Deadzone::ScaledRadial(1.5),
+    ] {
+        check(
+            config(deadzone),
+            &[((128, 128), (0.0, 0.0)), ((64, 128), (0.0, 0.0))],
+        );
+    }
+}
+
+#[test]
+fn outer_deadzone_reaches_full_output_early() {
+    let config = StickConfig {
+        outer_deadzone: 0.9,
+        ..StickConfig::raw()
+    };
+    check(
+        config,
+        &[
+            ((242, 128), (0.99738, 0.0)),
+            ((243, 128), (1.0, 0.0)),
+            ((255, 128), (1.0, 0.0)),
+            ((64, 128), (-0.55556, 0.0)),
+        ],
+    );
+}
+
+#[test]
+fn anti_deadzone_lifts_output_outside_the_deadzone() {
+    let config = StickConfig {
+        anti_deadzone: 0.2,
+        ..config(Deadzone::ScaledRadial(0.1))
+    };
+    check(
+        config,
+        &[
+            ((128, 128), (0.0, 0.0)),
+            ((140, 128), (0.0, 0.0)),
+            ((141, 128), (0.2021, 0.0)),
+            ((255, 128), (1.0, 0.0)),
+        ],
+    );
+}
+
+#[test]
+fn response_curves() {
+    fn double(value: f32) -> f32 {
+        value * 2.0
+    }
+    let cases = [
+        (ResponseCurve::Linear, [0.0, 0.002625, 0.44882, 1.0]),
+        (
+            ResponseCurve::Exponential(2.0),
+            [0.0, 0.0000069, 0.20144, 1.0],
+        ),
+        (ResponseCurve::Custom(double), [0.0, 0.00525, 0.89764, 1.0]),
+    ];
+    for (curve, [centre, edge, half, full]) in cases {
+        let config = StickConfig {
+            curve,
+            ..config(Deadzone::ScaledRadial(0.1))
+        };
+        check(
+            config,
+            &[
+                ((128, 128), (centre, 0.0)),
+                ((141, 128), (edge, 0.0)),
+                ((192, 128), (half, 0.0)),
+                ((255, 128), (full, 0.0)),
+            ],
+        );
+    }
+}
+
+#[test]
+fn curves_clamp_their_input_and_output() {
+    fn overshoot(_: f32) -> f32 {
+        3.0
+    }
+    assert_eq!(ResponseCurve::Linear.apply(-0.5), 0.0);
+    assert_eq!(ResponseCurve::Exponential(3.0).apply(1.5), 1.0);
+    assert_eq!(ResponseCurve::Custom(overshoot).apply(0.5), 1.0);
+}
+
+#[test]
+fn invert_y_makes_up_positive() {
+    let config = StickConfig {
+        invert_y: true,
+        ..StickConfig::raw()
+    };
+    check(config, &[((128, 0), (0.0, 1.0)), ((128, 255), (0.0, -1.0))]);
+}
@@ -0,0 +1,115 @@
+use rust_dualsense::{Finger, FingerTracker, InputState, TouchContact, TouchEvent};
+
+fn contact(id: u8, x: u16, y: u16) -> TouchContact {
+    TouchContact {
+        active: true,
+        id,
+        x,
+        y,
+    }
+}
+
+fn state(touch: [TouchContact; 2]) -> InputState {
+    InputState {
+        touch,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn parses_both_contacts() {
+    let mut report = InputState::default().to_usb_bytes();
+    // Contact 0: tracking id 5 at (0x123, 0x456); contact 1 lifted, id 6.
+    report[33..41].copy_from_slice(&[0x05, 0x23, 0x61, 0x45, 0x86, 0xff, 0xff, 0xff]);
+
+    let state = InputState::parse(&report).unwrap();
+    assert_eq!(state.touch[0], contact(5, 0x123, 0x456));
+    assert_eq!(
+        state.touch[1],
+        TouchContact {
+            active: false,
+            id: 6,
+            x: 0xfff,
+            y: 0xfff,
+        }
+    );
+    assert_eq!(state.to_usb_bytes(), report);
+}
+
+#[test]
+fn contacts_round_trip_over_bluetooth() {
+    let state = state([contact(0x7f, 1919, 1079), contact(0, 0, 0)]);
+    let parsed = InputState::parse(&state.to_bluetooth_bytes()).unwrap();
+    assert_eq!(parsed.touch, state.touch);
+}
+
+#[test]
+fn default_state_has_no_contacts() {
+    let parsed = InputState::parse(&InputState::default().to_usb_bytes()).unwrap();
+    assert!(parsed.touch.iter().all(|c| !c.active));
+}
+
+#[test]
+fn reports_down_move_and_up() {
+    let mut tracker = FingerTracker::new();
+    let lifted = TouchContact::default();
+
+    let events = tracker.update(&state([contact(3, 100, 200), lifted]));
+    let down = Finger {
+        id: 0,
+        tracking_id: 3,
+        x: 100,
+        y: 200,
+    };
+    assert_eq!(events, [TouchEvent::Down(down)]);
+
+    assert!(tracker
+        .update(&state([contact(3, 100, 200), lifted]))
+        .is_empty());
+
+    let events = tracker.update(&state([contact(3, 150, 210), lifted]));
+    let moved = Finger {
+        x: 150,
+        y: 210,
+        ..down
+    };
+    assert_eq!(
+        events,
+        [TouchEvent::Move {
+            finger: moved,
+            previous: down,
+        }]
+    );
+
+    let events = tracker.update(&state([lifted, lifted]));
+    assert_eq!(events, [TouchEvent::Up(moved)]);
+    assert!(tracker.fingers().is_empty());
+}
+
+#[test]
+fn finger_keeps_identity_when_changing_slots() {
+    let mut tracker = FingerTracker::new();
+    let lifted = TouchContact::default();
+    tracker.update(&state([contact(1, 10, 10), contact(2, 500, 500)]));
+
+    // The first finger lifts and the second moves into slot 0.
+    let events = tracker.update(&state([contact(2, 510, 500), lifted]));
+    assert_eq!(events.len(), 2);
+    assert!(matches!(events[0], TouchEvent::Up(f) if f.id == 0));
+    assert!(matches!(events[1], TouchEvent::Move { finger, .. } if finger.id == 1));
+    assert_eq!(tracker.fingers().len(), 1);
+    assert_eq!(tracker.fingers()[0].id, 1);
+}
+
+#[test]
+fn new_touch_in_same_slot_is_a_new_finger() {
+    let mut tracker = FingerTracker::new();
+    let lifted = TouchContact::default();
+    tracker.update(&state([contact(0x7f, 10, 10), lifted]));
+
+    // Lifted and touched again between two reports; the tracking id wraps.
+    let events = tracker.update(&state([contact(0, 10, 10), lifted]));
+    assert_eq!(events.len(), 2);
+    assert!(matches!(events[0], TouchEvent::Up(f) if f.id == 0 && f.tracking_id == 0x7f));
+    assert!(matches!(events[1], TouchEvent::Down(f) if f.id == 1 && f.tracking_id == 0));
+}
@@ -0,0 +1,73 @@
+use std::time::Duration;
+
+use rust_dualsense::{
+    Bus, Button, DualSense, Error, InputState, MockTransport, OutputReport, Transport,
+};
+
+fn pressed(button: Button) -> InputState {
+    let mut state = InputState::default();
+    state.buttons.set(button, true);
+    state
+}
+
+#[test]
+fn mock_hands_out_queued_inputs_in_order() {
+    let mut transport = MockTransport::new(Bus::Usb);
+    transport.push_input(&pressed(Button::Cross).to_usb_bytes());
+    transport.push_input(&pressed(Button::Circle).to_usb_bytes());
+    let mut dualsense = DualSense::new(transport);
+
+    let state = dualsense.read_state(Duration::ZERO).unwrap();
+    assert!(state.is_pressed(Button::Cross));
+    assert_eq!(dualsense.transport().pending_inputs(), 1);
+    let state = dualsense.read_state(Duration::ZERO).unwrap();
+    assert!(state.is_pressed(Button::Circle));
+    assert!(matches!(
+        dualsense.read_state(Duration::ZERO),
+        Err(Error::Timeout)
+    ));
+}
+
+#[test]
+fn mock_records_sent_outputs() {
+    let mut dualsense = DualSense::new(MockTransport::new(Bus::Usb));
+    let mut report = OutputReport::new();
+    report.rumble(0x20, 0x10).lightbar(0, 0, 0xff);
+    dualsense.send(&report).unwrap();
+
+    assert_eq!(
+        dualsense.transport().outputs(),
+        [report.to_usb_bytes().to_vec()]
+    );
+}
+
+#[test]
+fn bluetooth_outputs_advance_the_sequence() {
+    let mut dualsense = DualSense::new(MockTransport::new(Bus::Bluetooth));
+    let report = OutputReport::new();
+    for _ in 0..17 {
+        dualsense.send(&report).unwrap();
+    }
+
+    let outputs = dualsense.transport().outputs();
+    assert_eq!(outputs[0], report.to_bluetooth_bytes(0));
+    assert_eq!(outputs[1], report.to_bluetooth_bytes(1));
+    assert_eq!(outputs[16], report.to_bluetooth_bytes(0));
+}
+
+#[test]
+fn mock_answers_and_records_feature_reports() {
+    let mut transport = MockTransport::new(Bus::Usb);
+    transport.set_feature_response(&[0x09, 0xaa, 0xbb]);
+
+    let mut buf = [0; 8];
+    assert_eq!(transport.get_feature(0x09, &mut buf).unwrap(), 3);
+    assert_eq!(&buf[..3], &[0x09, 0xaa, 0xbb]);
+    assert!(matches!(
+        transport.get_feature(0x20, &mut buf),
+        Err(Error::UnsupportedReport(0x20))
+    ));
+
+    transport.set_feature(&[0x08, 0x01]).unwrap();
+    assert_eq!(transport.features_written(), [vec![0x08, 0x01]]);
+}